@@ -1,7 +1,9 @@
 static ROOTPATH: &str = "/sys/class/power_supply";
 
+/// A `Battery` or `UPS` power supply under `/sys/class/power_supply`.
 #[derive(Clone, Debug)]
 pub struct Battery {
+    /// Power supply node name, e.g. `BAT0`.
     pub name: String,
     manufacturer: String,
     model: String,
@@ -10,13 +12,16 @@ pub struct Battery {
 }
 
 impl Battery {
-    pub fn new(path: &str) -> std::io::Result<Battery> {
-        let path = std::path::Path::new(path);
-        let name = path.file_name().unwrap().to_str().unwrap().to_string();
-        let manufacturer = crate::utils::read_line(&path.join("manufacturer").to_str().unwrap().to_string())?;
-        let model = crate::utils::read_line(&path.join("model_name").to_str().unwrap().to_string())?;
-        let serial_number = crate::utils::read_line(&path.join("serial_number").to_str().unwrap().to_string())?;
-        let rtype = crate::utils::read_line(&path.join("type").to_str().unwrap().to_string())?;
+    /// Read the static attributes of the power supply at `path`.
+    pub fn new(path: &str) -> crate::error::Result<Battery> {
+        let name = match std::path::Path::new(path).file_name() {
+            Some(n) => n.to_string_lossy().to_string(),
+            None => return Err(crate::error::Error::NotFound(path.to_string())),
+        };
+        let manufacturer = crate::utils::read_value(&format!("{}/manufacturer", path))?;
+        let model = crate::utils::read_value(&format!("{}/model_name", path))?;
+        let serial_number = crate::utils::read_value(&format!("{}/serial_number", path))?;
+        let rtype = crate::utils::read_value(&format!("{}/type", path))?;
 
         Ok(Battery{
             name,
@@ -27,97 +32,274 @@ impl Battery {
         })
     }
 
-    pub fn capacity(&self) -> u32 {
-        if let Ok(raw_val) = crate::utils::read_line(&format!("{}/{}/capacity", ROOTPATH, self.name)) {
-            if let Ok(val) = raw_val.parse::<u32>() {
-                return val
-            } else {
-                return 0;
-            }
-        }
-        return 0;
+    pub fn manufacturer(&self) -> &str {
+        &self.manufacturer
     }
 
-    pub fn voltage_now(&self) -> u32 {
-        if let Ok(raw_val) = crate::utils::read_line(&format!("{}/{}/voltage_now", ROOTPATH, self.name)) {
-            if let Ok(val) = raw_val.parse::<u32>() {
-                return val
-            } else {
-                return 0;
-            }
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    pub fn serial_number(&self) -> &str {
+        &self.serial_number
+    }
+
+    /// Power supply `type`, `Battery` or `UPS`.
+    pub fn rtype(&self) -> &str {
+        &self.rtype
+    }
+
+    fn node(&self, attr: &str) -> String {
+        format!("{}/{}/{}", crate::utils::rootpath(ROOTPATH), self.name, attr)
+    }
+
+    /// State of charge in percent.
+    pub fn capacity(&self) -> crate::error::Result<u32> {
+        crate::utils::read_value(&self.node("capacity"))
+    }
+
+    /// Voltage in µV.
+    pub fn voltage_now(&self) -> crate::error::Result<u32> {
+        crate::utils::read_value(&self.node("voltage_now"))
+    }
+
+    /// Current in µA, negative while discharging.
+    ///
+    /// Drivers disagree on the sign of `current_now`, most report it
+    /// unsigned, so the sign is taken from `status`.
+    pub fn current_now(&self) -> crate::error::Result<i32> {
+        let current: i32 = crate::utils::read_value(&self.node("current_now"))?;
+        match self.status().as_deref() {
+            Ok("Discharging") => Ok(-current.abs()),
+            Ok(_) => Ok(current.abs()),
+            Err(_) => Ok(current),
         }
-        return 0;
     }
 
-    pub fn current_now(&self) -> u32 {
-        if let Ok(raw_val) = crate::utils::read_line(&format!("{}/{}/current_now", ROOTPATH, self.name)) {
-            if let Ok(val) = raw_val.parse::<u32>() {
-                return val
-            } else {
-                return 0;
-            }
+    /// Remaining energy in µWh, not every battery reports it, see
+    /// [`Battery::charge_now`].
+    pub fn energy_now(&self) -> crate::error::Result<u32> {
+        crate::utils::read_value(&self.node("energy_now"))
+    }
+
+    /// Remaining charge in µAh, reported instead of `energy_now` by some
+    /// batteries.
+    pub fn charge_now(&self) -> crate::error::Result<u32> {
+        crate::utils::read_value(&self.node("charge_now"))
+    }
+
+    /// Power in µW, computed from voltage and current when `power_now`
+    /// is missing.
+    pub fn power_now(&self) -> crate::error::Result<u32> {
+        match crate::utils::read_value(&self.node("power_now")) {
+            Err(crate::error::Error::NotFound(_)) => {},
+            r => return r,
         }
-        return 0;
+        let v = self.voltage_now()? as f64 / 1000.0;
+        let i = self.current_now()?.unsigned_abs() as f64 / 1000.0;
+        // mV * mA = µW
+        Ok((v * i) as u32)
     }
 
-    pub fn power_now(&self) -> u32 {
-        if let Ok(raw_val) = crate::utils::read_line(&format!("{}/{}/power_now", ROOTPATH, self.name)) {
-            if let Ok(val) = raw_val.parse::<u32>() {
-                return val
-            } else {
-                return 0;
-            }
+    /// Energy when fully charged in µWh, see [`Battery::charge_full`].
+    pub fn energy_full(&self) -> crate::error::Result<u32> {
+        crate::utils::read_value(&self.node("energy_full"))
+    }
+
+    /// Energy when fully charged as designed in µWh.
+    pub fn energy_full_design(&self) -> crate::error::Result<u32> {
+        crate::utils::read_value(&self.node("energy_full_design"))
+    }
+
+    /// Charge when fully charged in µAh, for batteries without `energy_*`.
+    pub fn charge_full(&self) -> crate::error::Result<u32> {
+        crate::utils::read_value(&self.node("charge_full"))
+    }
+
+    /// Charge when fully charged as designed in µAh.
+    pub fn charge_full_design(&self) -> crate::error::Result<u32> {
+        crate::utils::read_value(&self.node("charge_full_design"))
+    }
+
+    /// Minimal design voltage in µV, converts `charge_*` to energy.
+    pub fn voltage_min_design(&self) -> crate::error::Result<u32> {
+        crate::utils::read_value(&self.node("voltage_min_design"))
+    }
+
+    /// Charge/discharge cycles, 0 on batteries that don't count them.
+    pub fn cycle_count(&self) -> crate::error::Result<u32> {
+        crate::utils::read_value(&self.node("cycle_count"))
+    }
+
+    /// Cell chemistry, e.g. `Li-ion` or `Li-poly`.
+    pub fn technology(&self) -> crate::error::Result<String> {
+        crate::utils::read_value(&self.node("technology"))
+    }
+
+    // converts `charge_*` µAh to µWh
+    fn charge_volts(&self) -> crate::error::Result<f64> {
+        self.voltage_min_design().or(self.voltage_now()).map(|uv| uv as f64 / 1e6)
+    }
+
+    /// Remaining and full energy in Wh, from `energy_*` or else
+    /// `charge_*` at the design voltage.
+    pub fn energy(&self) -> crate::error::Result<(f64, f64)> {
+        match (self.energy_now(), self.energy_full()) {
+            (Ok(now), Ok(full)) => Ok((now as f64 / 1e6, full as f64 / 1e6)),
+            (Err(e), _) | (_, Err(e)) => {
+                let (now, full) = match (self.charge_now(), self.charge_full()) {
+                    (Ok(now), Ok(full)) => (now, full),
+                    _ => return Err(e),
+                };
+                let volts = self.charge_volts()?;
+                Ok((now as f64 * volts / 1e6, full as f64 * volts / 1e6))
+            },
         }
-        let v = self.voltage_now() as f64 / 1000.0;
-        let i = self.current_now() as f64 / 1000.0;
-        return (v * i / 1000.0) as u32;
     }
 
-    pub fn status(&self) -> String {
-        if let Ok(raw_val) = crate::utils::read_line(&format!("{}/{}/status", ROOTPATH, self.name)) {
-            return raw_val;
+    /// Wear of the battery from its full and design capacity.
+    pub fn health(&self) -> Health {
+        // charge in µAh times volts gives µWh
+        let (full, design, volts) = match self.energy_full() {
+            Ok(full) => (Some(full), self.energy_full_design().ok(), Some(1.0)),
+            Err(_) => (self.charge_full().ok(), self.charge_full_design().ok(), self.charge_volts().ok()),
+        };
+        let wh = |uwh: Option<u32>| Some(uwh? as f64 * volts? / 1e6);
+        let health_percent = match (full, design) {
+            (Some(full), Some(design)) if design > 0 => Some(full as f64 * 100.0 / design as f64),
+            _ => None,
+        };
+
+        Health {
+            name: self.name.clone(),
+            manufacturer: self.manufacturer.clone(),
+            model: self.model.clone(),
+            serial_number: self.serial_number.clone(),
+            technology: self.technology().ok(),
+            cycle_count: self.cycle_count().ok(),
+            full_wh: wh(full),
+            design_wh: wh(design),
+            health_percent,
+            wear_percent: health_percent.map(|h| 100.0 - h),
         }
-        return "".to_string();
+    }
+
+    /// `Charging`, `Discharging`, `Full`, `Not charging` or `Unknown`.
+    pub fn status(&self) -> crate::error::Result<String> {
+        crate::utils::read_value(&self.node("status"))
     }
 }
 
-pub fn enumerate() -> Vec<Battery> {
-    fn is_battery(entry: &walkdir::DirEntry) -> bool {
-        if entry.path().to_str().unwrap() == ROOTPATH {
+/// Health report of a battery, see [`Battery::health`].
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct Health {
+    pub name: String,
+    pub manufacturer: String,
+    pub model: String,
+    pub serial_number: String,
+    pub technology: Option<String>,
+    pub cycle_count: Option<u32>,
+    /// Capacity when fully charged today.
+    pub full_wh: Option<f64>,
+    pub design_wh: Option<f64>,
+    /// Full capacity in % of the design capacity.
+    pub health_percent: Option<f64>,
+    /// `100 - health_percent`.
+    pub wear_percent: Option<f64>,
+}
+
+impl std::fmt::Display for Health {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let or_unknown = |v: Option<String>| v.unwrap_or_else(|| "unknown".to_string());
+        writeln!(f, "{}: {} {} (serial {})", self.name, self.manufacturer, self.model, self.serial_number)?;
+        writeln!(f, "  technology:      {}", or_unknown(self.technology.clone()))?;
+        writeln!(f, "  cycle count:     {}", or_unknown(self.cycle_count.map(|c| c.to_string())))?;
+        writeln!(f, "  design capacity: {}", or_unknown(self.design_wh.map(|wh| format!("{:.2} Wh", wh))))?;
+        writeln!(f, "  full capacity:   {}", or_unknown(self.full_wh.map(|wh| format!("{:.2} Wh", wh))))?;
+        writeln!(f, "  health:          {}", or_unknown(self.health_percent.map(|p| format!("{:.1}%", p))))?;
+        write!(f, "  wear level:      {}", or_unknown(self.wear_percent.map(|p| format!("{:.1}%", p))))
+    }
+}
+
+/// A `Mains` power supply, e.g. the AC adapter.
+#[derive(Clone, Debug)]
+pub struct Mains {
+    /// Power supply node name, e.g. `AC` or `ADP1`.
+    pub name: String,
+}
+
+impl Mains {
+    /// Whether the adapter is plugged in.
+    pub fn online(&self) -> crate::error::Result<bool> {
+        let path = format!("{}/{}/online", crate::utils::rootpath(ROOTPATH), self.name);
+        crate::utils::read_value::<u8>(&path).map(|v| v != 0)
+    }
+}
+
+// power supplies whose `type` is one of `types`
+fn enumerate_supplies(types: &[&str]) -> Vec<std::path::PathBuf> {
+    fn is_supply(rootpath: &str, entry: &walkdir::DirEntry, types: &[&str]) -> bool {
+        if entry.path().to_str().unwrap() == rootpath {
             return false;
         }
         let realpath = if entry.file_type().is_symlink() {
-            let paths = std::fs::read_link(entry.path()).unwrap();
-            std::path::Path::new(ROOTPATH).join(paths).canonicalize().unwrap()
+            match std::fs::read_link(entry.path()).and_then(|p| std::path::Path::new(rootpath).join(p).canonicalize()) {
+                Ok(p) => p,
+                Err(_) => return false,
+            }
         } else if entry.file_type().is_dir() {
             entry.path().to_path_buf()
         } else {
             return false;
         };
 
-        let path = realpath.join("type").to_str().unwrap().to_string();
-        if let Ok(name) = crate::utils::read_line(&path) {
-            let n = name.trim();
-            if n == "Battery" || n == "UPS" {
+        if let Ok(name) = crate::utils::read_line(&realpath.join("type").to_string_lossy()) {
+            if types.contains(&name.trim()) {
                 return true;
             }
         }
 
-        return false;
+        false
     }
 
+    let rootpath = crate::utils::rootpath(ROOTPATH);
+    walkdir::WalkDir::new(&rootpath)
+        .sort_by_file_name()
+        .max_depth(1)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|e|  (e.file_type().is_dir() || e.file_type().is_symlink()) && is_supply(&rootpath, e, types))
+        .map(|e| e.path().to_path_buf())
+        .collect()
+}
+
+/// Enumerate every battery and UPS power supply.
+pub fn enumerate() -> Vec<Battery> {
     let mut batterys = Vec::new();
-    for entry in walkdir::WalkDir::new(ROOTPATH)
-            .sort_by_file_name()
-            .max_depth(1)
-            .into_iter()
-            .filter_map(Result::ok)
-            .filter(|e|  (e.file_type().is_dir() || e.file_type().is_symlink()) && is_battery(e)) {
-                let full_path =  entry.path().to_str().expect("is not path").to_string();
-                if let Ok(battery) = Battery::new(&full_path) {
-                    batterys.push(battery);
-                }
+    for path in enumerate_supplies(&["Battery", "UPS"]) {
+        let full_path =  path.to_str().expect("is not path").to_string();
+        if let Ok(battery) = Battery::new(&full_path) {
+            batterys.push(battery);
+        }
     }
     
-    return batterys
-}
\ No newline at end of file
+    batterys
+}
+
+/// Enumerate every `Mains` power supply.
+pub fn enumerate_mains() -> Vec<Mains> {
+    enumerate_supplies(&["Mains"])
+        .iter()
+        .filter_map(|path| path.file_name())
+        .map(|name| Mains { name: name.to_string_lossy().to_string() })
+        .collect()
+}
+
+/// Whether the system runs on battery: no AC adapter online and at
+/// least one battery discharging.
+pub fn on_battery() -> bool {
+    if enumerate_mains().iter().any(|ac| ac.online().unwrap_or(false)) {
+        return false;
+    }
+    enumerate().iter().any(|b| b.status().is_ok_and(|s| s == "Discharging"))
+}
@@ -0,0 +1,69 @@
+// /sys/block and /proc/diskstats
+use std::collections::HashMap;
+
+static ROOTPATH: &str = "/sys/block";
+
+/// Names of the block devices under `/sys/block`, loop devices excluded.
+pub fn enumerate() -> Vec<String> {
+    let mut disks = Vec::new();
+    let rootpath = crate::utils::rootpath(ROOTPATH);
+    for entry in walkdir::WalkDir::new(&rootpath)
+            .min_depth(1)
+            .max_depth(1)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_map(Result::ok)
+            .filter(|e|  e.file_type().is_dir() || e.file_type().is_symlink()) {
+                let name = entry.file_name().to_string_lossy().to_string();
+                if !name.starts_with("loop") {
+                    disks.push(name);
+                }
+    }
+    disks
+}
+
+/// Cumulative counters of one `/proc/diskstats` line.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DiskStat {
+    pub reads: u64,
+    pub read_sectors: u64,
+    pub read_ms: u64,
+    pub writes: u64,
+    pub write_sectors: u64,
+    pub write_ms: u64,
+    /// Time the device had I/O in flight.
+    pub io_ms: u64,
+}
+
+impl DiskStat {
+    // fields after `major minor name`
+    fn parse(fields: &[&str]) -> Option<Self> {
+        let val = |i: usize| fields.get(i).and_then(|v| v.parse::<u64>().ok());
+        Some(DiskStat {
+            reads: val(0)?,
+            read_sectors: val(2)?,
+            read_ms: val(3)?,
+            writes: val(4)?,
+            write_sectors: val(6)?,
+            write_ms: val(7)?,
+            io_ms: val(9)?,
+        })
+    }
+}
+
+/// Read `/proc/diskstats` keyed by device name. Sectors are 512 bytes.
+pub fn diskstats() -> crate::error::Result<HashMap<String, DiskStat>> {
+    let path = crate::utils::rootpath("/proc/diskstats");
+    let data = crate::utils::read_all_line(&path).map_err(|e| crate::error::Error::from_io(&path, e))?;
+
+    let mut stats = HashMap::new();
+    for line in data.lines() {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let stat = match fields.get(3..).and_then(DiskStat::parse) {
+            Some(stat) => stat,
+            None => return Err(crate::error::Error::Parse { path, value: line.to_string() }),
+        };
+        stats.insert(fields[2].to_string(), stat);
+    }
+    Ok(stats)
+}
@@ -1,171 +1,1113 @@
+/// Time of one sampling cycle, shared by every collector of the cycle.
+#[derive(Clone, Copy, Debug)]
+pub struct Timestamp {
+    /// Wall clock in milliseconds since the unix epoch.
+    pub millis: u64,
+    /// Monotonic milliseconds since collecting started.
+    pub elapsed: u64,
+}
+
+impl Timestamp {
+    /// Leading csv columns of every collector.
+    pub fn header() -> Vec<String> {
+        vec!["timestamp_ms".to_string(), "elapsed_ms".to_string()]
+    }
+
+    pub fn record(&self) -> Vec<String> {
+        vec![self.millis.to_string(), self.elapsed.to_string()]
+    }
+}
+
+/// Drift-compensated sampling schedule.
+///
+/// Ticks are placed at fixed offsets from the start, so the time spent in
+/// `update` doesn't accumulate. Ticks missed because a cycle overran are
+/// skipped rather than run back to back.
+pub struct Clock {
+    start: std::time::Instant,
+    interval: std::time::Duration,
+    next: std::time::Instant,
+}
+
+impl Clock {
+    pub fn new(interval: std::time::Duration) -> Self {
+        let start = std::time::Instant::now();
+        Self { start, interval, next: start }
+    }
+
+    pub fn now(&self) -> Timestamp {
+        let since_epoch = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default();
+        Timestamp {
+            millis: since_epoch.as_millis() as u64,
+            elapsed: self.start.elapsed().as_millis() as u64,
+        }
+    }
+
+    /// Sleep until the next tick.
+    pub fn wait(&mut self) {
+        self.wait_unless(|| false);
+    }
+
+    /// Sleep until the next tick, or until `stop` returns true, checked
+    /// every 100 ms so a long interval doesn't delay a shutdown.
+    pub fn wait_unless(&mut self, stop: impl Fn() -> bool) {
+        self.advance(std::time::Instant::now());
+        loop {
+            let now = std::time::Instant::now();
+            if now >= self.next || stop() {
+                break;
+            }
+            std::thread::sleep((self.next - now).min(std::time::Duration::from_millis(100)));
+        }
+    }
+
+    // move to the first tick after the current one that isn't before `now`
+    fn advance(&mut self, now: std::time::Instant) {
+        self.next += self.interval;
+        while self.next < now {
+            self.next += self.interval;
+        }
+    }
+}
+
+// `Collector::output` and `output_mut` of a collector with an `output` field
+macro_rules! output_accessors {
+    () => {
+        fn output(&self) -> &Output {
+            &self.output
+        }
+
+        fn output_mut(&mut self) -> &mut Output {
+            &mut self.output
+        }
+    };
+}
+
+/// A sampler that appends one csv record per `update`.
 pub trait Collector {
-    fn update(&mut self) -> std::io::Result<()>;
-    fn need_stop(&self) -> bool;
+    /// Take one sample stamped with `ts` and write it out.
+    fn update(&mut self, ts: &Timestamp) -> crate::error::Result<()>;
+    /// Why collecting should stop after the latest sample, if it should.
+    fn need_stop(&self) -> Option<String> {
+        None
+    }
+    /// The csv file written, with the latest sample.
+    fn output(&self) -> &Output;
+    fn output_mut(&mut self) -> &mut Output;
+}
+
+/// Last column of every collector csv, holding the reason collecting
+/// stopped in the final record.
+pub const STOP_COLUMN: &str = "stop";
+
+/// A collector csv file that keeps its latest record, e.g. for
+/// [`crate::stop::StopRule`].
+pub struct Output {
+    file: String,
+    name: String,
+    writer: csv::Writer<std::fs::File>,
+    header: Vec<String>,
+    last: Vec<String>,
+    stop: Option<String>,
 }
 
+impl Output {
+    /// Create `file` and write `header` to it.
+    pub fn new(file: &str, header: Vec<String>) -> crate::error::Result<Self> {
+        let mut writer = csv::Writer::from_path(file).map_err(|e| crate::error::Error::from_csv(file, e))?;
+        let mut record = header.clone();
+        record.push(STOP_COLUMN.to_string());
+        writer.write_record(record).map_err(|e| crate::error::Error::from_csv(file, e))?;
+        let name = std::path::Path::new(file).file_stem()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        Ok(Self { file: file.to_string(), name, writer, header, last: Vec::new(), stop: None })
+    }
+
+    /// File name without `.csv`, e.g. `thermal`.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Column names, the stop column excluded.
+    pub fn header(&self) -> &[String] {
+        &self.header
+    }
+
+    /// Latest value of `column`, `None` before the first record or for
+    /// an empty cell.
+    pub fn value(&self, column: &str) -> Option<&str> {
+        let index = self.header.iter().position(|h| h == column)?;
+        self.last.get(index).map(|v| v.as_str()).filter(|v| !v.is_empty())
+    }
+
+    /// Append `record` and flush it to disk.
+    pub fn write(&mut self, record: Vec<String>) -> crate::error::Result<()> {
+        let mut row = record.clone();
+        row.push(self.stop.take().unwrap_or_default());
+        self.writer.write_record(row).map_err(|e| crate::error::Error::from_csv(&self.file, e))?;
+        self.writer.flush().map_err(|e| crate::error::Error::from_io(&self.file, e))?;
+        self.last = record;
+        Ok(())
+    }
+
+    /// Record `reason` in the stop column of the next record.
+    pub fn set_stop(&mut self, reason: &str) {
+        self.stop = Some(reason.to_string());
+    }
+}
+
+/// How a [`sample`] loop ended.
+#[derive(Clone, Debug)]
+pub struct Summary {
+    /// Samples taken, the final one included.
+    pub samples: u64,
+    pub start: Timestamp,
+    /// Time of the final sample.
+    pub stop: Timestamp,
+    /// Why sampling stopped, `None` once `count` samples were taken.
+    pub reason: Option<String>,
+}
+
+/// Sample every collector on each tick of `clock`, `count` times or until
+/// a collector needs to stop, a rule holds or `interrupt` gives a reason.
+/// `interrupt` is polled while waiting for a tick too, e.g. for signals.
+///
+/// Ends with one more sample whose stop column holds the reason.
+pub fn sample(collectors: &mut [Box<dyn Collector>], clock: &mut Clock, count: Option<u64>, rules: &mut [crate::stop::StopRule], interrupt: impl Fn() -> Option<String>) -> crate::error::Result<Summary> {
+    let start = clock.now();
+    let mut n = 0;
+    let mut reason = None;
+    while count.is_none_or(|count| n < count) {
+        // an interrupt lets the previous cycle finish, then takes the
+        // final sample
+        if let Some(why) = interrupt() {
+            reason = Some(why);
+            break;
+        }
+        n += 1;
+        let ts = clock.now();
+        for c in collectors.iter_mut() {
+            c.update(&ts)?;
+            if reason.is_none() {
+                reason = c.need_stop();
+            }
+        }
+        let outputs: Vec<&Output> = collectors.iter().map(|c| c.output()).collect();
+        // every rule sees every sample, so `for` durations stay accurate
+        for rule in rules.iter_mut() {
+            if rule.check(&outputs, ts.elapsed) && reason.is_none() {
+                reason = Some(rule.to_string());
+            }
+        }
+        clock.wait_unless(|| interrupt().is_some());
+        // the final sample still waits for its tick, so deltas cover a
+        // full interval
+        if reason.is_some() {
+            break;
+        }
+    }
+
+    // do once at end, the last record of every csv tells why it stopped
+    if let Some(reason) = &reason {
+        for c in collectors.iter_mut() {
+            c.output_mut().set_stop(reason);
+        }
+    }
+    let stop = clock.now();
+    for c in collectors.iter_mut() {
+        c.update(&stop)?;
+    }
+    Ok(Summary { samples: n + 1, start, stop, reason })
+}
+
+// a reading that couldn't be taken is left as an empty cell
+fn cell<T: ToString>(val: crate::error::Result<T>) -> String {
+    match val {
+        Ok(v) => v.to_string(),
+        Err(_) => "".to_string(),
+    }
+}
+
+// Delta collectors keep their last sample as `(elapsed, value)`. The first
+// sample, or a failed read, has no delta to report, so these give None and
+// the cells are left empty.
+
+// the last sample and the ms since it was taken
+fn previous<'a, T>(last: Option<&'a (u64, T)>, ts: &Timestamp) -> Option<(&'a T, u64)> {
+    last.filter(|(elapsed, _)| ts.elapsed > *elapsed)
+        .map(|(elapsed, value)| (value, ts.elapsed - elapsed))
+}
+
+// growth of a counter between two samples
+fn delta(prev: Option<u64>, cur: Option<u64>) -> Option<u64> {
+    Some(cur?.saturating_sub(prev?))
+}
+
+/// Current frequency of each cpu.
 pub struct CpuCollector {
     cpus: Vec<crate::cpu::CPU>,
-    writer: csv::Writer<std::fs::File>,
+    output: Output,
 }
 
 impl CpuCollector {
-    pub fn new(cpus: Vec<crate::cpu::CPU>,file: &str) -> std::io::Result<Self> {
-        let mut writer = csv::Writer::from_path(file)?;
-        let mut header = vec!["timestamp".to_string()];
+    pub fn new(cpus: Vec<crate::cpu::CPU>,file: &str) -> crate::error::Result<Self> {
+        let mut header = Timestamp::header();
         for cpu in &cpus {
             header.push(cpu.tag().clone());
         }
-        writer.write_record(header)?;
-        Ok(Self { cpus, writer })
+        let output = Output::new(file, header)?;
+        Ok(Self { cpus, output })
     }
 }
 
 impl Collector for CpuCollector {
-    fn update(&mut self) -> std::io::Result<()>{
-        let since_epoch = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)
-            .expect("Time went backwards");
-
-        // 以秒为单位
-        let timestamp = since_epoch.as_secs();
-
-        let mut record = vec![timestamp.to_string()];
-        if self.cpus.len() > 0 {
+    fn update(&mut self, ts: &Timestamp) -> crate::error::Result<()>{
+        let mut record = ts.record();
+        if !self.cpus.is_empty() {
             for cpu in &self.cpus {
-                record.push(cpu.freq().to_string());
+                record.push(cell(cpu.freq()));
             }
         }
-        self.writer.write_record(record)?;
-        self.writer.flush()
+        self.output.write(record)
     }
 
-    fn need_stop(&self) -> bool {
-        false
-    }
+    output_accessors!();
 }
 
+/// State of charge of each battery and of all of them combined, stops
+/// once the combined value drops to `min` percent.
+///
+/// The combined value weighs each battery by its energy, so a small
+/// internal battery doesn't count as much as a large external one.
 pub struct CapacityCollector {
     batterys: Vec<crate::battery::Battery>,
-    writer: csv::Writer<std::fs::File>,
-    last_capacity: u32,
+    output: Output,
+    last_capacity: f64,
     min_capacity: u32,
 }
 
 impl CapacityCollector {
-    pub fn new(batterys: Vec<crate::battery::Battery>,file: &str, min: u32) -> std::io::Result<Self> {
-        let mut writer = csv::Writer::from_path(file)?;
-        let mut header = vec!["timestamp".to_string()];
+    pub fn new(batterys: Vec<crate::battery::Battery>,file: &str, min: u32) -> crate::error::Result<Self> {
+        let mut header = Timestamp::header();
         for battery in &batterys {
             header.push(battery.name.clone());
         }
-        writer.write_record(header)?;
-        Ok(Self { batterys, writer, last_capacity:100 ,min_capacity: 5})
+        header.push("combined".to_string());
+        let output = Output::new(file, header)?;
+        Ok(Self { batterys, output, last_capacity: 100.0, min_capacity: min})
+    }
+
+    // summed energy_now over summed energy_full, falls back to the plain
+    // average of `capacity` when a battery reports neither energy nor charge
+    fn combined(&self, capacitys: &[crate::error::Result<u32>]) -> Option<f64> {
+        let energys: crate::error::Result<Vec<(f64, f64)>> = self.batterys.iter().map(|b| b.energy()).collect();
+        if let Ok(energys) = energys {
+            let now: f64 = energys.iter().map(|(now, _)| now).sum();
+            let full: f64 = energys.iter().map(|(_, full)| full).sum();
+            if full > 0.0 {
+                return Some(now * 100.0 / full);
+            }
+        }
+        let read: Vec<u32> = capacitys.iter().filter_map(|c| c.as_ref().ok().copied()).collect();
+        match read.len() {
+            0 => None,
+            n => Some(read.iter().sum::<u32>() as f64 / n as f64),
+        }
     }
 }
 
 impl Collector for CapacityCollector {
-    fn update(&mut self) -> std::io::Result<()>{
-        let since_epoch = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)
-            .expect("Time went backwards");
-
-        // 以秒为单位
-        let timestamp = since_epoch.as_secs();
-
-        let mut record = vec![timestamp.to_string()];
-        if self.batterys.len() > 0 {
-            let mut sum_capacity = 0;
-            for battery in &self.batterys {
-                let cap = battery.capacity();
-                sum_capacity = sum_capacity + cap;
-                record.push(cap.to_string());
-            }
+    fn update(&mut self, ts: &Timestamp) -> crate::error::Result<()>{
+        let mut record = ts.record();
+        let capacitys: Vec<crate::error::Result<u32>> = self.batterys.iter().map(|b| b.capacity()).collect();
+        for cap in &capacitys {
+            record.push(cap.as_ref().map(|v| v.to_string()).unwrap_or_default());
+        }
 
-            self.last_capacity = sum_capacity / self.batterys.len() as u32;
+        // keep the last known capacity if no battery could be read
+        let combined = self.combined(&capacitys);
+        if let Some(combined) = combined {
+            self.last_capacity = combined;
         }
-        self.writer.write_record(record)?;
-        self.writer.flush()
+        record.push(combined.map(|c| format!("{:.1}", c)).unwrap_or_default());
+        self.output.write(record)
     }
 
-    fn need_stop(&self) -> bool {
-        self.last_capacity <= self.min_capacity
+    fn need_stop(&self) -> Option<String> {
+        if self.last_capacity <= self.min_capacity as f64 {
+            return Some(format!("capacity.combined <= {}", self.min_capacity));
+        }
+        None
     }
+
+    output_accessors!();
 }
 
+/// Power draw of each battery.
 pub struct PowerCollector {
     batterys: Vec<crate::battery::Battery>,
-    writer: csv::Writer<std::fs::File>,
+    output: Output,
 }
 
 impl PowerCollector {
-    pub fn new(batterys: Vec<crate::battery::Battery>,file: &str) -> std::io::Result<Self> {
-        let mut writer = csv::Writer::from_path(file)?;
-        let mut header = vec!["timestamp".to_string()];
+    pub fn new(batterys: Vec<crate::battery::Battery>,file: &str) -> crate::error::Result<Self> {
+        let mut header = Timestamp::header();
         for battery in &batterys {
             header.push(battery.name.clone());
         }
-        writer.write_record(header)?;
-        Ok(Self { batterys, writer })
+        let output = Output::new(file, header)?;
+        Ok(Self { batterys, output })
     }
 }
 
 impl Collector for PowerCollector {
-    fn update(&mut self) -> std::io::Result<()>{
-        let since_epoch = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)
-            .expect("Time went backwards");
+    fn update(&mut self, ts: &Timestamp) -> crate::error::Result<()>{
+        let mut record = ts.record();
+        if !self.batterys.is_empty() {
+            for battery in &self.batterys {
+                record.push(cell(battery.power_now()));
+            }
+        }
+        self.output.write(record)
+    }
 
-        // 以秒为单位
-        let timestamp = since_epoch.as_secs();
+    output_accessors!();
+}
 
-        let mut record = vec![timestamp.to_string()];
-        if self.batterys.len() > 0 {
-            for battery in &self.batterys {
-                record.push(battery.power_now().to_string());
+/// Status, energy or charge, voltage and signed current of each battery,
+/// and whether each AC adapter is online.
+pub struct BatteryCollector {
+    batterys: Vec<crate::battery::Battery>,
+    mains: Vec<crate::battery::Mains>,
+    output: Output,
+}
+
+impl BatteryCollector {
+    // energy_uWh or charge_uAh is left empty when the battery reports the other
+    const COLUMNS: [&'static str; 5] = ["status", "energy_uWh", "charge_uAh", "voltage_uV", "current_uA"];
+
+    pub fn new(batterys: Vec<crate::battery::Battery>, mains: Vec<crate::battery::Mains>, file: &str) -> crate::error::Result<Self> {
+        let mut header = Timestamp::header();
+        for battery in &batterys {
+            for column in Self::COLUMNS {
+                header.push(format!("{}:{}", battery.name, column));
             }
         }
-        self.writer.write_record(record)?;
-        self.writer.flush()
+        for ac in &mains {
+            header.push(format!("{}:online", ac.name));
+        }
+        let output = Output::new(file, header)?;
+        Ok(Self { batterys, mains, output })
     }
+}
 
-    fn need_stop(&self) -> bool {
-        false
+impl Collector for BatteryCollector {
+    fn update(&mut self, ts: &Timestamp) -> crate::error::Result<()>{
+        let mut record = ts.record();
+        for battery in &self.batterys {
+            record.push(cell(battery.status()));
+            record.push(cell(battery.energy_now()));
+            record.push(cell(battery.charge_now()));
+            record.push(cell(battery.voltage_now()));
+            record.push(cell(battery.current_now()));
+        }
+        for ac in &self.mains {
+            record.push(cell(ac.online().map(u8::from)));
+        }
+        self.output.write(record)
     }
+
+    output_accessors!();
 }
 
+/// Temperature of each thermal zone.
 pub struct ThermalCollector {
     thermals: Vec<crate::thermal::Thermal>,
-    writer: csv::Writer<std::fs::File>,
+    output: Output,
 }
 
 impl ThermalCollector {
-    pub fn new(thermals: Vec<crate::thermal::Thermal>,file: &str) -> std::io::Result<Self> {
-        let mut writer = csv::Writer::from_path(file)?;
-        let mut header = vec!["timestamp".to_string()];
+    pub fn new(thermals: Vec<crate::thermal::Thermal>,file: &str) -> crate::error::Result<Self> {
+        let mut header = Timestamp::header();
         for thermal in &thermals {
             header.push(thermal.name.clone());
         }
-        writer.write_record(header)?;
-        Ok(Self { thermals, writer })
+        let output = Output::new(file, header)?;
+        Ok(Self { thermals, output })
     }
 }
 
 impl Collector for ThermalCollector {
-    fn update(&mut self) -> std::io::Result<()>{
-        let since_epoch = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)
-            .expect("Time went backwards");
+    fn update(&mut self, ts: &Timestamp) -> crate::error::Result<()>{
+        let mut record = ts.record();
+        if !self.thermals.is_empty() {
+            for thermal in &self.thermals {
+                record.push(cell(thermal.temp()));
+            }
+        }
+        self.output.write(record)
+    }
 
-        // 以秒为单位
-        let timestamp = since_epoch.as_secs();
+    output_accessors!();
+}
 
-        let mut record = vec![timestamp.to_string()];
-        if self.thermals.len() > 0 {
-            for thermal in &self.thermals {
-                record.push(thermal.temp().to_string());
+/// Every sensor of one class, e.g. all fans, of one hwmon chip.
+pub struct HwmonCollector {
+    sensors: Vec<crate::hwmon::Sensor>,
+    output: Output,
+}
+
+impl HwmonCollector {
+    pub fn new(hwmon: &crate::hwmon::Hwmon, class: crate::hwmon::SensorClass, file: &str) -> crate::error::Result<Self> {
+        // sensors are fixed at start so the columns stay stable
+        let sensors: Vec<crate::hwmon::Sensor> = hwmon.sensors()
+            .into_iter()
+            .filter(|s| s.class == class)
+            .collect();
+
+        let mut header = Timestamp::header();
+        for sensor in &sensors {
+            header.push(format!("{}:{}", hwmon.id, sensor.name()));
+        }
+        let output = Output::new(file, header)?;
+        Ok(Self { sensors, output })
+    }
+}
+
+impl Collector for HwmonCollector {
+    fn update(&mut self, ts: &Timestamp) -> crate::error::Result<()>{
+        let mut record = ts.record();
+        for sensor in &self.sensors {
+            record.push(cell(sensor.value()));
+        }
+        self.output.write(record)
+    }
+
+    output_accessors!();
+}
+
+/// Utilization of each cpu and of all cpus, from `/proc/stat` deltas.
+pub struct CpuLoadCollector {
+    cpus: Vec<crate::cpu::CPU>,
+    last: Option<crate::cpu::Stat>,
+    output: Output,
+}
+
+impl CpuLoadCollector {
+    // percent columns written for each cpu, `user` includes `nice`
+    const STATES: [&'static str; 7] = ["user", "system", "iowait", "irq", "softirq", "idle", "steal"];
+
+    pub fn new(cpus: Vec<crate::cpu::CPU>,file: &str) -> crate::error::Result<Self> {
+        let mut header = Timestamp::header();
+        let mut tags = vec!["all".to_string()];
+        tags.extend(cpus.iter().map(|cpu| cpu.tag()));
+        for tag in &tags {
+            for state in Self::STATES {
+                header.push(format!("{}:{}", tag, state));
             }
         }
-        self.writer.write_record(record)?;
-        self.writer.flush()
+        let output = Output::new(file, header)?;
+        Ok(Self { cpus, last: None, output })
+    }
+
+    fn percents(prev: Option<&crate::cpu::CpuTimes>, cur: Option<&crate::cpu::CpuTimes>) -> Vec<String> {
+        let (prev, cur) = match (prev, cur) {
+            (Some(prev), Some(cur)) if cur.total() > prev.total() => (prev, cur),
+            _ => return vec!["".to_string(); Self::STATES.len()],
+        };
+        let total = (cur.total() - prev.total()) as f64;
+        let percent = |c: u64, p: u64| format!("{:.1}", c.saturating_sub(p) as f64 * 100.0 / total);
+        vec![
+            percent(cur.user + cur.nice, prev.user + prev.nice),
+            percent(cur.system, prev.system),
+            percent(cur.iowait, prev.iowait),
+            percent(cur.irq, prev.irq),
+            percent(cur.softirq, prev.softirq),
+            percent(cur.idle, prev.idle),
+            percent(cur.steal, prev.steal),
+        ]
     }
+}
 
-    fn need_stop(&self) -> bool {
-        false
+impl Collector for CpuLoadCollector {
+    fn update(&mut self, ts: &Timestamp) -> crate::error::Result<()>{
+        let mut record = ts.record();
+        let stat = crate::cpu::stat().ok();
+        let last = self.last.as_ref();
+        record.extend(Self::percents(last.map(|s| &s.total), stat.as_ref().map(|s| &s.total)));
+        for cpu in &self.cpus {
+            let prev = last.and_then(|s| s.cpus.get(&cpu.number()));
+            let cur = stat.as_ref().and_then(|s| s.cpus.get(&cpu.number()));
+            record.extend(Self::percents(prev, cur));
+        }
+        self.last = stat;
+        self.output.write(record)
     }
-}
\ No newline at end of file
+
+    output_accessors!();
+}
+
+/// Per-cpu residency in % of each cpuidle state, plus the time spent
+/// out of them as `active`.
+pub struct CStateCollector {
+    cpus: Vec<crate::cpu::CPU>,
+    // state count of each cpu, as written in the header
+    states: Vec<usize>,
+    last: Option<(u64, std::collections::HashMap<u32, Vec<u64>>)>,
+    output: Output,
+}
+
+impl CStateCollector {
+    pub fn new(cpus: Vec<crate::cpu::CPU>,file: &str) -> crate::error::Result<Self> {
+        let mut header = Timestamp::header();
+        let mut states = Vec::new();
+        // cpus without cpuidle, or with unreadable states, are left out
+        let cpus: Vec<crate::cpu::CPU> = cpus.into_iter()
+            .filter(|cpu| cpu.idle_states().is_ok_and(|s| !s.is_empty()))
+            .collect();
+        for cpu in &cpus {
+            let idle_states = cpu.idle_states()?;
+            for state in &idle_states {
+                header.push(format!("{}:{}", cpu.tag(), state.name));
+            }
+            header.push(format!("{}:active", cpu.tag()));
+            states.push(idle_states.len());
+        }
+        let output = Output::new(file, header)?;
+        Ok(Self { cpus, states, last: None, output })
+    }
+}
+
+impl Collector for CStateCollector {
+    fn update(&mut self, ts: &Timestamp) -> crate::error::Result<()>{
+        let mut record = ts.record();
+        let mut times = std::collections::HashMap::new();
+        for (cpu, &count) in self.cpus.iter().zip(self.states.iter()) {
+            let cur: Option<Vec<u64>> = cpu.idle_states().ok()
+                .filter(|states| states.len() == count)
+                .map(|states| states.iter().map(|s| s.time).collect());
+            let prev = previous(self.last.as_ref(), ts)
+                .and_then(|(last, ms)| last.get(&cpu.number()).map(|times| (times, ms)));
+            match (prev, &cur) {
+                (Some((prev, ms)), Some(cur)) => {
+                    let interval_us = (ms * 1000) as f64;
+                    let percents: Vec<f64> = cur.iter().zip(prev.iter())
+                        .map(|(c, p)| (c.saturating_sub(*p) as f64 * 100.0 / interval_us).min(100.0))
+                        .collect();
+                    let active = (100.0 - percents.iter().sum::<f64>()).max(0.0);
+                    record.extend(percents.iter().map(|p| format!("{:.1}", p)));
+                    record.push(format!("{:.1}", active));
+                },
+                _ => record.extend(vec!["".to_string(); count + 1]),
+            }
+            if let Some(cur) = cur {
+                times.insert(cpu.number(), cur);
+            }
+        }
+        self.last = Some((ts.elapsed, times));
+        self.output.write(record)
+    }
+
+    output_accessors!();
+}
+
+/// Memory and swap usage from `/proc/meminfo`, paging and fault rates
+/// from `/proc/vmstat` deltas.
+pub struct MemoryCollector {
+    last: Option<(u64, std::collections::HashMap<String, u64>)>,
+    output: Output,
+}
+
+impl MemoryCollector {
+    // kB, SwapUsed is SwapTotal - SwapFree
+    const MEMINFO: [&'static str; 6] = ["MemTotal", "MemAvailable", "Cached", "Dirty", "Writeback", "SwapUsed"];
+    // events per second
+    const VMSTAT: [&'static str; 5] = ["pgpgin", "pgpgout", "pswpin", "pswpout", "pgfault"];
+
+    pub fn new(file: &str) -> crate::error::Result<Self> {
+        let mut header = Timestamp::header();
+        header.extend(Self::MEMINFO.iter().map(|k| format!("{}_kB", k)));
+        header.extend(Self::VMSTAT.iter().map(|k| format!("{}/s", k)));
+        let output = Output::new(file, header)?;
+        Ok(Self { last: None, output })
+    }
+}
+
+impl Collector for MemoryCollector {
+    fn update(&mut self, ts: &Timestamp) -> crate::error::Result<()>{
+        let mut record = ts.record();
+        let meminfo = crate::memory::meminfo().unwrap_or_default();
+        for key in Self::MEMINFO {
+            let val = if key == "SwapUsed" {
+                match (meminfo.get("SwapTotal"), meminfo.get("SwapFree")) {
+                    (Some(total), Some(free)) => Some(total.saturating_sub(*free)),
+                    _ => None,
+                }
+            } else {
+                meminfo.get(key).copied()
+            };
+            record.push(val.map(|v| v.to_string()).unwrap_or_default());
+        }
+
+        let vmstat = crate::memory::vmstat().ok();
+        let prev = previous(self.last.as_ref(), ts);
+        for key in Self::VMSTAT {
+            let rate = prev.and_then(|(prev, ms)| {
+                let count = delta(prev.get(key).copied(), vmstat.as_ref()?.get(key).copied())?;
+                Some(count as f64 * 1000.0 / ms as f64)
+            });
+            record.push(rate.map(|v| format!("{:.1}", v)).unwrap_or_default());
+        }
+        self.last = vmstat.map(|v| (ts.elapsed, v));
+
+        self.output.write(record)
+    }
+
+    output_accessors!();
+}
+
+/// Pressure stall information of cpu, memory and io, and the load
+/// average.
+pub struct PressureCollector {
+    last: Option<(u64, std::collections::HashMap<&'static str, crate::pressure::Pressure>)>,
+    output: Output,
+}
+
+impl PressureCollector {
+    // avg10 as reported, stall% from the `total` delta over the interval
+    const COLUMNS: [&'static str; 4] = ["some_avg10", "some_stall%", "full_avg10", "full_stall%"];
+    const LOADAVG: [&'static str; 4] = ["load1", "load5", "load15", "running"];
+
+    pub fn new(file: &str) -> crate::error::Result<Self> {
+        let mut header = Timestamp::header();
+        for resource in crate::pressure::RESOURCES {
+            for column in Self::COLUMNS {
+                header.push(format!("{}:{}", resource, column));
+            }
+        }
+        header.extend(Self::LOADAVG.iter().map(|k| k.to_string()));
+        let output = Output::new(file, header)?;
+        Ok(Self { last: None, output })
+    }
+}
+
+impl Collector for PressureCollector {
+    fn update(&mut self, ts: &Timestamp) -> crate::error::Result<()>{
+        let mut record = ts.record();
+        let mut pressures = std::collections::HashMap::new();
+        for resource in crate::pressure::RESOURCES {
+            let cur = crate::pressure::pressure(resource).ok();
+            let prev = previous(self.last.as_ref(), ts)
+                .and_then(|(last, ms)| last.get(resource).map(|p| (p, ms)));
+            let stall = |p: Option<&crate::pressure::Psi>, c: Option<&crate::pressure::Psi>| {
+                let us = delta(p.map(|p| p.total), c.map(|c| c.total));
+                match (prev, us) {
+                    (Some((_, ms)), Some(us)) => format!("{:.2}", (us as f64 * 100.0 / (ms * 1000) as f64).min(100.0)),
+                    _ => "".to_string(),
+                }
+            };
+            let some = cur.as_ref().map(|c| &c.some);
+            let full = cur.as_ref().and_then(|c| c.full.as_ref());
+            record.push(some.map(|p| format!("{:.2}", p.avg10)).unwrap_or_default());
+            record.push(stall(prev.map(|(p, _)| &p.some), some));
+            record.push(full.map(|p| format!("{:.2}", p.avg10)).unwrap_or_default());
+            record.push(stall(prev.and_then(|(p, _)| p.full.as_ref()), full));
+            if let Some(cur) = cur {
+                pressures.insert(resource, cur);
+            }
+        }
+        self.last = Some((ts.elapsed, pressures));
+
+        match crate::pressure::loadavg() {
+            Ok(load) => {
+                record.push(format!("{:.2}", load.load1));
+                record.push(format!("{:.2}", load.load5));
+                record.push(format!("{:.2}", load.load15));
+                record.push(load.running.to_string());
+            },
+            Err(_) => record.extend(vec!["".to_string(); Self::LOADAVG.len()]),
+        }
+
+        self.output.write(record)
+    }
+
+    output_accessors!();
+}
+
+/// Throughput, IOPS, utilization and await of each disk, from
+/// `/proc/diskstats` deltas.
+pub struct DiskCollector {
+    disks: Vec<String>,
+    last: Option<(u64, std::collections::HashMap<String, crate::block::DiskStat>)>,
+    output: Output,
+}
+
+impl DiskCollector {
+    const COLUMNS: [&'static str; 6] = ["rkB/s", "wkB/s", "r/s", "w/s", "util%", "await_ms"];
+
+    pub fn new(disks: Vec<String>,file: &str) -> crate::error::Result<Self> {
+        let mut header = Timestamp::header();
+        for disk in &disks {
+            for column in Self::COLUMNS {
+                header.push(format!("{}:{}", disk, column));
+            }
+        }
+        let output = Output::new(file, header)?;
+        Ok(Self { disks, last: None, output })
+    }
+
+    fn rates(prev: Option<&crate::block::DiskStat>, cur: Option<&crate::block::DiskStat>, ms: u64) -> Vec<String> {
+        let (prev, cur) = match (prev, cur) {
+            (Some(prev), Some(cur)) if ms > 0 => (prev, cur),
+            _ => return vec!["".to_string(); Self::COLUMNS.len()],
+        };
+        let per_sec = |c: u64, p: u64| c.saturating_sub(p) as f64 * 1000.0 / ms as f64;
+        let ios = cur.reads.saturating_sub(prev.reads) + cur.writes.saturating_sub(prev.writes);
+        let io_ms = cur.read_ms.saturating_sub(prev.read_ms) + cur.write_ms.saturating_sub(prev.write_ms);
+        let wait = if ios > 0 { io_ms as f64 / ios as f64 } else { 0.0 };
+        vec![
+            format!("{:.1}", per_sec(cur.read_sectors, prev.read_sectors) / 2.0),
+            format!("{:.1}", per_sec(cur.write_sectors, prev.write_sectors) / 2.0),
+            format!("{:.1}", per_sec(cur.reads, prev.reads)),
+            format!("{:.1}", per_sec(cur.writes, prev.writes)),
+            format!("{:.1}", (per_sec(cur.io_ms, prev.io_ms) / 10.0).min(100.0)),
+            format!("{:.2}", wait),
+        ]
+    }
+}
+
+impl Collector for DiskCollector {
+    fn update(&mut self, ts: &Timestamp) -> crate::error::Result<()>{
+        let mut record = ts.record();
+        let stats = crate::block::diskstats().ok();
+        for disk in &self.disks {
+            let (prev, ms) = match previous(self.last.as_ref(), ts) {
+                Some((prev, ms)) => (prev.get(disk), ms),
+                None => (None, 0),
+            };
+            let cur = stats.as_ref().and_then(|s| s.get(disk));
+            record.extend(Self::rates(prev, cur, ms));
+        }
+        self.last = stats.map(|s| (ts.elapsed, s));
+        self.output.write(record)
+    }
+
+    output_accessors!();
+}
+
+/// Throughput, packet rate, errors and drops of each network interface,
+/// from `/proc/net/dev` deltas.
+pub struct NetCollector {
+    ifaces: Vec<String>,
+    last: Option<(u64, std::collections::HashMap<String, crate::net::NetStat>)>,
+    output: Output,
+}
+
+impl NetCollector {
+    // errors and drops are counts within the interval
+    const COLUMNS: [&'static str; 8] = ["rx_bytes/s", "tx_bytes/s", "rx_packets/s", "tx_packets/s", "rx_errs", "tx_errs", "rx_drop", "tx_drop"];
+
+    pub fn new(ifaces: Vec<String>,file: &str) -> crate::error::Result<Self> {
+        let mut header = Timestamp::header();
+        for iface in &ifaces {
+            for column in Self::COLUMNS {
+                header.push(format!("{}:{}", iface, column));
+            }
+        }
+        let output = Output::new(file, header)?;
+        Ok(Self { ifaces, last: None, output })
+    }
+
+    fn rates(prev: Option<&crate::net::NetStat>, cur: Option<&crate::net::NetStat>, ms: u64) -> Vec<String> {
+        let (prev, cur) = match (prev, cur) {
+            (Some(prev), Some(cur)) if ms > 0 => (prev, cur),
+            _ => return vec!["".to_string(); Self::COLUMNS.len()],
+        };
+        let per_sec = |c: u64, p: u64| format!("{:.1}", c.saturating_sub(p) as f64 * 1000.0 / ms as f64);
+        let delta = |c: u64, p: u64| c.saturating_sub(p).to_string();
+        vec![
+            per_sec(cur.rx_bytes, prev.rx_bytes),
+            per_sec(cur.tx_bytes, prev.tx_bytes),
+            per_sec(cur.rx_packets, prev.rx_packets),
+            per_sec(cur.tx_packets, prev.tx_packets),
+            delta(cur.rx_errs, prev.rx_errs),
+            delta(cur.tx_errs, prev.tx_errs),
+            delta(cur.rx_drop, prev.rx_drop),
+            delta(cur.tx_drop, prev.tx_drop),
+        ]
+    }
+}
+
+impl Collector for NetCollector {
+    fn update(&mut self, ts: &Timestamp) -> crate::error::Result<()>{
+        let mut record = ts.record();
+        let stats = crate::net::netdev().ok();
+        for iface in &self.ifaces {
+            let (prev, ms) = match previous(self.last.as_ref(), ts) {
+                Some((prev, ms)) => (prev.get(iface), ms),
+                None => (None, 0),
+            };
+            let cur = stats.as_ref().and_then(|s| s.get(iface));
+            record.extend(Self::rates(prev, cur, ms));
+        }
+        self.last = stats.map(|s| (ts.elapsed, s));
+        self.output.write(record)
+    }
+
+    output_accessors!();
+}
+
+/// Power of each RAPL zone in W, from `energy_uj` deltas.
+pub struct RaplCollector {
+    zones: Vec<crate::rapl::RaplZone>,
+    last: Vec<Option<(u64, u64)>>,
+    output: Output,
+}
+
+impl RaplCollector {
+    pub fn new(zones: Vec<crate::rapl::RaplZone>,file: &str) -> crate::error::Result<Self> {
+        let mut header = Timestamp::header();
+        for zone in &zones {
+            header.push(zone.name.clone());
+        }
+        let output = Output::new(file, header)?;
+        let last = vec![None; zones.len()];
+        Ok(Self { zones, last, output })
+    }
+}
+
+impl Collector for RaplCollector {
+    fn update(&mut self, ts: &Timestamp) -> crate::error::Result<()>{
+        let mut record = ts.record();
+        for (zone, last) in self.zones.iter().zip(self.last.iter_mut()) {
+            let energy = zone.energy_uj().ok();
+            let power = match (previous(last.as_ref(), ts), energy) {
+                (Some((prev, ms)), Some(cur)) => {
+                    let uj = zone.energy_delta(*prev, cur) as f64;
+                    Some(uj / (ms as f64 * 1000.0))
+                },
+                _ => None,
+            };
+            record.push(power.map(|w| format!("{:.3}", w)).unwrap_or_default());
+            *last = energy.map(|e| (ts.elapsed, e));
+        }
+        self.output.write(record)
+    }
+
+    output_accessors!();
+}
+
+/// GPU frequency and RC6 residency of Intel cards, load and shader clock
+/// of amdgpu cards.
+pub struct GpuCollector {
+    gpus: Vec<crate::gpu::Gpu>,
+    // rc6_residency_ms of each card at the last sample
+    last: Option<(u64, Vec<Option<u64>>)>,
+    output: Output,
+}
+
+impl GpuCollector {
+    const INTEL: [&'static str; 3] = ["cur_freq_MHz", "act_freq_MHz", "rc6%"];
+    const AMD: [&'static str; 2] = ["busy%", "sclk_MHz"];
+
+    /// Cards of other drivers are left out.
+    pub fn new(gpus: Vec<crate::gpu::Gpu>,file: &str) -> crate::error::Result<Self> {
+        let mut header = Timestamp::header();
+        let gpus: Vec<crate::gpu::Gpu> = gpus.into_iter()
+            .filter(|gpu| gpu.kind != crate::gpu::GpuKind::Other)
+            .collect();
+        for gpu in &gpus {
+            let columns: &[&str] = match gpu.kind {
+                crate::gpu::GpuKind::Intel => &Self::INTEL,
+                _ => &Self::AMD,
+            };
+            for column in columns {
+                header.push(format!("{}:{}", gpu.card, column));
+            }
+        }
+        let output = Output::new(file, header)?;
+        Ok(Self { gpus, last: None, output })
+    }
+}
+
+impl Collector for GpuCollector {
+    fn update(&mut self, ts: &Timestamp) -> crate::error::Result<()>{
+        let mut record = ts.record();
+        let mut residencies = Vec::new();
+        for (i, gpu) in self.gpus.iter().enumerate() {
+            match gpu.kind {
+                crate::gpu::GpuKind::Intel => {
+                    record.push(cell(gpu.cur_freq()));
+                    record.push(cell(gpu.act_freq()));
+                    let residency = gpu.rc6_residency_ms().ok();
+                    let rc6 = previous(self.last.as_ref(), ts)
+                        .and_then(|(last, ms)| Some((delta(last[i], residency)?, ms)))
+                        .map(|(rc6_ms, ms)| format!("{:.1}", (rc6_ms as f64 * 100.0 / ms as f64).min(100.0)))
+                        .unwrap_or_default();
+                    record.push(rc6);
+                    residencies.push(residency);
+                },
+                _ => {
+                    record.push(cell(gpu.busy_percent()));
+                    record.push(cell(gpu.sclk()));
+                    residencies.push(None);
+                },
+            }
+        }
+        self.last = Some((ts.elapsed, residencies));
+        self.output.write(record)
+    }
+
+    output_accessors!();
+}
+
+/// Context switch, hard and soft irq rates, overall and per cpu, plus
+/// the `top` busiest interrupt sources of each interval.
+pub struct InterruptCollector {
+    cpus: Vec<u32>,
+    top: usize,
+    last: Option<(u64, InterruptSample)>,
+    output: Output,
+}
+
+struct InterruptSample {
+    ctxt: Option<u64>,
+    irqs: Option<crate::interrupts::Interrupts>,
+    softirqs: Option<crate::interrupts::Interrupts>,
+}
+
+type InterruptDeltas = std::collections::HashMap<String, Vec<u64>>;
+
+impl InterruptCollector {
+    // written for each of the top sources: its name, rate and busiest cpu
+    const TOP_COLUMNS: [&'static str; 3] = ["source", "/s", "cpu"];
+
+    /// `cpus` are the `CPUN` columns of `/proc/interrupts`, see
+    /// [`crate::interrupts::Interrupts::cpus`].
+    pub fn new(cpus: Vec<u32>, top: usize, file: &str) -> crate::error::Result<Self> {
+        let mut header = Timestamp::header();
+        header.extend(["ctxt/s", "irq/s", "softirq/s"].iter().map(|k| k.to_string()));
+        for cpu in &cpus {
+            header.push(format!("cpu{}:irq/s", cpu));
+            header.push(format!("cpu{}:softirq/s", cpu));
+        }
+        for i in 1..=top {
+            for column in Self::TOP_COLUMNS {
+                header.push(format!("top{}:{}", i, column));
+            }
+        }
+        let output = Output::new(file, header)?;
+        Ok(Self { cpus, top, last: None, output })
+    }
+
+    // per source and per cpu deltas, None if the cpus came or went
+    fn deltas(prev: Option<&crate::interrupts::Interrupts>, cur: Option<&crate::interrupts::Interrupts>) -> Option<InterruptDeltas> {
+        let (prev, cur) = (prev?, cur?);
+        if prev.cpus != cur.cpus {
+            return None;
+        }
+        Some(cur.sources.iter()
+            .map(|(source, counts)| {
+                let zero = vec![0; counts.len()];
+                let prev = prev.sources.get(source).unwrap_or(&zero);
+                (source.clone(), counts.iter().zip(prev).map(|(c, p)| c.saturating_sub(*p)).collect())
+            })
+            .collect())
+    }
+}
+
+impl Collector for InterruptCollector {
+    fn update(&mut self, ts: &Timestamp) -> crate::error::Result<()>{
+        let mut record = ts.record();
+        let cur = InterruptSample {
+            ctxt: crate::cpu::stat().ok().map(|s| s.ctxt),
+            irqs: crate::interrupts::interrupts().ok(),
+            softirqs: crate::interrupts::softirqs().ok(),
+        };
+        let (prev, secs) = match previous(self.last.as_ref(), ts) {
+            Some((prev, ms)) => (Some(prev), ms as f64 / 1000.0),
+            None => (None, 0.0),
+        };
+        let rate = |count: u64| format!("{:.1}", count as f64 / secs);
+        let irq_deltas = prev.and_then(|prev| Self::deltas(prev.irqs.as_ref(), cur.irqs.as_ref()));
+        let softirq_deltas = prev.and_then(|prev| Self::deltas(prev.softirqs.as_ref(), cur.softirqs.as_ref()));
+
+        let ctxt = delta(prev.and_then(|prev| prev.ctxt), cur.ctxt);
+        record.push(ctxt.map(rate).unwrap_or_default());
+        record.push(irq_deltas.as_ref().map(|d| rate(d.values().flatten().sum())).unwrap_or_default());
+        record.push(softirq_deltas.as_ref().map(|d| rate(d.values().flatten().sum())).unwrap_or_default());
+
+        // by cpu number, the online cpus may differ from the header
+        let per_cpu = |interrupts: Option<&crate::interrupts::Interrupts>, deltas: Option<&InterruptDeltas>, cpu: u32| {
+            let index = interrupts?.cpus.iter().position(|c| *c == cpu)?;
+            Some(rate(deltas?.values().map(|counts| counts[index]).sum()))
+        };
+        for cpu in &self.cpus {
+            record.push(per_cpu(cur.irqs.as_ref(), irq_deltas.as_ref(), *cpu).unwrap_or_default());
+            record.push(per_cpu(cur.softirqs.as_ref(), softirq_deltas.as_ref(), *cpu).unwrap_or_default());
+        }
+
+        let mut sources: Vec<(String, u64, u32)> = Vec::new();
+        for (prefix, interrupts, deltas) in [("", &cur.irqs, &irq_deltas), ("softirq/", &cur.softirqs, &softirq_deltas)] {
+            if let (Some(interrupts), Some(deltas)) = (interrupts, deltas) {
+                for (source, counts) in deltas {
+                    if let Some((i, _)) = counts.iter().enumerate().max_by_key(|(_, c)| **c) {
+                        sources.push((format!("{}{}", prefix, source), counts.iter().sum(), interrupts.cpus[i]));
+                    }
+                }
+            }
+        }
+        sources.retain(|(_, count, _)| *count > 0);
+        sources.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        for i in 0..self.top {
+            match sources.get(i) {
+                Some((source, count, cpu)) => {
+                    record.push(source.clone());
+                    record.push(rate(*count));
+                    record.push(cpu.to_string());
+                },
+                None => record.extend(vec!["".to_string(); Self::TOP_COLUMNS.len()]),
+            }
+        }
+
+        self.last = Some((ts.elapsed, cur));
+        self.output.write(record)
+    }
+
+    output_accessors!();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn clock() -> Clock {
+        Clock::new(Duration::from_millis(100))
+    }
+
+    #[test]
+    fn advance_keeps_the_schedule() {
+        let mut clock = clock();
+        let start = clock.start;
+        // a short cycle waits for the next tick, not interval after it
+        clock.advance(start + Duration::from_millis(30));
+        assert_eq!(clock.next, start + Duration::from_millis(100));
+        clock.advance(start + Duration::from_millis(180));
+        assert_eq!(clock.next, start + Duration::from_millis(200));
+    }
+
+    #[test]
+    fn advance_skips_missed_ticks() {
+        let mut clock = clock();
+        let start = clock.start;
+        // a cycle that overran 100, 200 and 300 goes on at 400
+        clock.advance(start + Duration::from_millis(350));
+        assert_eq!(clock.next, start + Duration::from_millis(400));
+        // a cycle ending right on a tick doesn't skip it
+        clock.advance(start + Duration::from_millis(500));
+        assert_eq!(clock.next, start + Duration::from_millis(500));
+    }
+}
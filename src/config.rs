@@ -11,12 +11,24 @@ bitflags!{
     }
 }
 
+type Handler = Rc<Box<dyn Fn(&str) -> String>>;
+type Validator = Rc<Box<dyn Fn(&str) -> crate::error::Result<()>>>;
+
+/// A tunable sysfs/procfs node and its value.
+///
+/// `node` is the path on the target system, independent of
+/// [`crate::utils::root`], so configs saved on one machine apply on another.
 #[derive(Clone)]
 pub struct Config {
     pub node: String,
+    /// `None` when the node could not be read.
     pub value: Option<String>,
     permission: PERMISSION,
-    pub handler: Rc<Box<dyn Fn(&str) -> String>>,
+    /// Converts a shown value into the value written back, e.g.
+    /// `[none] mq-deadline` -> `none`.
+    pub handler: Handler,
+    // checks the value written by `apply`, against the live system
+    validator: Option<Validator>,
 }
 
 impl Config {
@@ -24,37 +36,47 @@ impl Config {
         Self::new_with_handler(node, |s| {s.to_string()})
     }
 
-    fn new_with_handler<F: 'static>(node: &str,f: F)  -> Config where
-        F: Fn(&str) -> String {
-        let value  = match crate::utils::read_line(node) {
-            Ok(v) => Some(v),
-            Err(e) => None,
-        };
+    fn new_with_handler<F>(node: &str,f: F)  -> Config where
+        F: Fn(&str) -> String + 'static {
+        let value  = crate::utils::read_line(&crate::utils::rootpath(node)).ok();
 
         Config {
             node: node.to_string(),
             value,
             permission: PERMISSION::READ,
             handler: Rc::new(Box::new(f)),
+            validator: None,
         }
     }
 
     fn add_permission(mut self, perm: PERMISSION) -> Self {
-        self.permission = self.permission | perm;
+        self.permission |= perm;
+        self
+    }
+
+    fn with_validator<F>(mut self, f: F) -> Self where
+        F: Fn(&str) -> crate::error::Result<()> + 'static {
+        self.validator = Some(Rc::new(Box::new(f)));
         self
     }
 
-    pub fn apply(&self) ->std::io::Result<()> {
+    /// Write `value` through `handler` to the node, rejecting values the
+    /// node's validator refuses.
+    pub fn apply(&self) -> crate::error::Result<()> {
         match &self.value {
             Some(val) => {
-                let raw_val = (self.handler)(&val);
-                crate::utils::write_line(&self.node, &raw_val)?;
-                Ok(())
+                let raw_val = (self.handler)(val);
+                if let Some(validator) = &self.validator {
+                    validator(&raw_val).map_err(|e| crate::error::Error::Invalid(format!("{}: {}", self.node, e)))?;
+                }
+                let path = crate::utils::rootpath(&self.node);
+                crate::utils::write_line(&path, &raw_val).map_err(|e| crate::error::Error::from_io(&path, e))
             },
-            None => Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "val is none")),
+            None => Err(crate::error::Error::Invalid(format!("{}: val is none", self.node))),
         }
     }
 
+    /// Whether the node was readable and may be written by `apply`.
     pub fn writeable(&self) -> bool {
         match &self.value {
             Some(_) => {
@@ -64,8 +86,10 @@ impl Config {
         }
     }
 
-    pub fn load(infile: &str) -> Vec<Self> {
-        let file = std::fs::File::open(infile).unwrap();
+    /// Load configs from the output of `config show`, i.e. the nodes
+    /// between the two `----------` lines.
+    pub fn load(infile: &str) -> crate::error::Result<Vec<Self>> {
+        let file = std::fs::File::open(infile).map_err(|e| crate::error::Error::from_io(infile, e))?;
         let mut f = std::io::BufReader::new(file);
         
         let mut configs = Vec::new();
@@ -74,7 +98,7 @@ impl Config {
         let mut new_config = false;
         loop {
             let mut buf = String::new();
-            let len = f.read_line(&mut buf).unwrap();
+            let len = f.read_line(&mut buf).map_err(|e| crate::error::Error::from_io(infile, e))?;
             if len == 0 {
                 break;
             }
@@ -88,9 +112,9 @@ impl Config {
             } else if start {
                 if buf.is_empty() {
                     continue;
-                } else if buf.starts_with("+ ") {
+                } else if let Some(node) = buf.strip_prefix("+ ") {
                     new_config = true;
-                    config.node = buf[2..].to_string();
+                    config.node = node.to_string();
                 } else if new_config {
                     new_config = false;
                     if buf.starts_with("ERROR:") || buf.starts_with("WARNING:") {
@@ -102,7 +126,7 @@ impl Config {
                 }
             }
         }
-        configs
+        Ok(configs)
     }
 }
 
@@ -118,6 +142,89 @@ impl std::fmt::Display for Config {
     }
 }
 
+fn run_cmd_with_echo(cmd: &str, args: Vec<&str>) -> crate::error::Result<()> {
+    println!(">>> {} {}", cmd, args.join(" "));
+    println!("{}", crate::utils::run_cmd(cmd, args).map_err(|e| crate::error::Error::from_io(cmd, e))?);
+    Ok(())
+}
+
+fn read_line_with_echo(path: &str) {
+    println!("+ {}", path);
+    match crate::utils::read_line(path) {
+        Ok(v) => println!("{}", v),
+        Err(e) => println!("ERROR: {} {}", path, e),
+    };
+}
+
+fn read_all_wich_echo(path: &str) {
+    println!("+ {}", path);
+    match crate::utils::read_all_line(path) {
+        Ok(v) => println!("{}", v),
+        Err(e) => println!("ERROR: {} {}", path, e),
+    };
+}
+
+fn show_os_info() -> crate::error::Result<()> {
+    read_all_wich_echo(&crate::utils::rootpath("/etc/os-release"));
+    run_cmd_with_echo("uname", vec!["-a"])?;
+
+    if let Ok(session_desktop) = std::env::var("XDG_SESSION_DESKTOP") {
+        println!("XDG_SESSION_DESKTOP={}", session_desktop);
+    }
+
+    if let Ok(session_type) = std::env::var("XDG_SESSION_TYPE") {
+        println!("XDG_SESSION_TYPE={}", session_type);
+    }
+
+    Ok(())
+}
+
+fn show_dim_info() {
+    read_line_with_echo(&crate::utils::rootpath("/sys/class/dmi/id/product_version"));
+    read_line_with_echo(&crate::utils::rootpath("/sys/class/dmi/id/product_name"));
+    read_line_with_echo(&crate::utils::rootpath("/sys/class/dmi/id/sys_vendor"));
+    read_line_with_echo(&crate::utils::rootpath("/sys/class/dmi/id/bios_version"));
+    read_line_with_echo(&crate::utils::rootpath("/sys/class/dmi/id/ec_firmware_release"));
+}
+
+/// Print the os and dmi info, every config and the batteries, thermal
+/// zones and hwmon sensors, in the format [`Config::load`] reads back.
+pub fn show() -> crate::error::Result<()> {
+    show_os_info()?;
+    show_dim_info();
+    println!("--------------");
+    let configs = enumerate();
+    for config in configs {
+        println!("{}", config);
+    }
+    println!("--------------");
+
+    let bats = crate::battery::enumerate();
+    if !bats.is_empty() {
+        println!(">> battery <<");
+        println!("{:#?}", bats);
+    }
+
+    let thermals = crate::thermal::enumerate();
+    if !thermals.is_empty() {
+        println!(">> thermal <<");
+        println!("{:#?}", thermals);
+    }
+
+    let hwmons = crate::hwmon::enumerate();
+    for hwmon in hwmons {
+        let sensors = hwmon.sensors();
+        if !sensors.is_empty() {
+            println!(">> hwmon {} <<", hwmon.id);
+            for sensor in sensors {
+                println!("{}", sensor);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Enumerate every known tunable node.
 pub fn enumerate() -> Vec<Config> {
     let mut configs = Vec::new();
 
@@ -135,15 +242,91 @@ pub fn enumerate() -> Vec<Config> {
     configs.append(&mut enumerate_pcie_aspm());
     configs.append(&mut enumerate_net_wakeup());
     configs.append(&mut enumerate_usb_wakeup());
+    configs.append(&mut enumerate_battery());
     
     configs
 }
 
+// the value of a `[selected]` list, e.g. `[none] mq-deadline` -> `none`
+fn selected(s: &str) -> String {
+    let start = match s.find("[") {
+        Some(s) => s + 1,
+        None => 0,
+    };
+    let end = match s.find("]") {
+        Some(e) => e,
+        None => s.len(),
+    };
+
+    s[start..end].to_string()
+}
+
+/// Order `changes` so that each battery's charge thresholds keep
+/// start < end after every single write.
+///
+/// Raising the thresholds must write the end first, lowering them the
+/// start first, or the driver refuses the intermediate state.
+pub fn sort_changes(changes: &mut [Config]) {
+    for i in 0..changes.len() {
+        let dir = match changes[i].node.strip_suffix("/charge_control_start_threshold") {
+            Some(dir) => dir.to_string(),
+            None => continue,
+        };
+        let end_node = format!("{}/charge_control_end_threshold", dir);
+        let j = match changes.iter().position(|c| c.node == end_node) {
+            Some(j) => j,
+            None => continue,
+        };
+        let current_start = read_threshold(&changes[i].node).ok();
+        let new_end = changes[j].value.as_deref().and_then(|v| v.trim().parse::<u32>().ok());
+        let end_first = match (current_start, new_end) {
+            (Some(start), Some(end)) => end > start,
+            _ => false,
+        };
+        if end_first != (j < i) {
+            changes.swap(i, j);
+        }
+    }
+}
+
+/// Apply the nodes of `file`, as written by `config show`, whose value
+/// differs from the local one, in [`sort_changes`] order.
+pub fn apply_file(file: &str) -> crate::error::Result<()> {
+    let configs = Config::load(file)?;
+    let local_configs = enumerate();
+
+    let mut need_change = Vec::new();
+    for config in &configs {
+        for local_config in &local_configs {
+            if config.node == local_config.node && local_config.value.is_some() && config.value != local_config.value && local_config.writeable() {
+                let mut config_new = local_config.clone();
+                config_new.value = config.value.clone();
+                need_change.push(config_new);
+            }
+        }
+    }
+
+    sort_changes(&mut need_change);
+    for config in need_change {
+        if let Some(val) = &config.value {
+            println!("apply: {} = {}", config.node, val);
+            if let Err(e) = config.apply() {
+                println!("ERROR: apply {} failed {}", config.node, e)
+            }
+        }
+    }
+    Ok(())
+}
+
+fn read_threshold(path: &str) -> crate::error::Result<u32> {
+    crate::utils::read_value(&crate::utils::rootpath(path))
+}
+
 fn enumerate_cpu() -> Vec<Config> {
     let mut configs = Vec::new();
 
-    fn is_cpuname(entry: &walkdir::DirEntry) -> bool {
-        if entry.path().to_str().unwrap() == "/sys/devices/system/cpu" {
+    fn is_cpuname(rootpath: &str, entry: &walkdir::DirEntry) -> bool {
+        if entry.path().to_str().unwrap() == rootpath {
             return false;
         }
         entry.file_name()
@@ -155,13 +338,14 @@ fn enumerate_cpu() -> Vec<Config> {
              .unwrap_or(false)
     }
 
-    for entry in walkdir::WalkDir::new("/sys/devices/system/cpu")
+    let rootpath = crate::utils::rootpath("/sys/devices/system/cpu");
+    for entry in walkdir::WalkDir::new(&rootpath)
             .max_depth(1)
             .sort_by_file_name()
             .into_iter()
             .filter_map(Result::ok)
-            .filter(|e|  (e.file_type().is_dir() || e.file_type().is_symlink()) && is_cpuname(e)) {
-                let fullpath = entry.path().to_str().unwrap();
+            .filter(|e|  (e.file_type().is_dir() || e.file_type().is_symlink()) && is_cpuname(&rootpath, e)) {
+                let fullpath = crate::utils::logicpath(entry.path());
                 configs.push(Config::new(&format!("{}/cpufreq/scaling_driver", fullpath)));
                 configs.push(Config::new(&format!("{}/cpufreq/scaling_governor", fullpath)).add_permission(PERMISSION::WRITE));
                 configs.push(Config::new(&format!("{}/cpufreq/scaling_available_governors", fullpath)));
@@ -190,13 +374,13 @@ fn enumerate_cpu() -> Vec<Config> {
 }
 
 fn enumerate_vm() -> Vec<Config> {
-    let mut configs = Vec::new();
-    configs.push(Config::new("/proc/sys/vm/laptop_mode").add_permission(PERMISSION::WRITE));
-    configs.push(Config::new("/proc/sys/vm/dirty_writeback_centisecs").add_permission(PERMISSION::WRITE));
-    configs.push(Config::new("/proc/sys/vm/dirty_expire_centisecs").add_permission(PERMISSION::WRITE));
-    configs.push(Config::new("/proc/sys/vm/dirty_ratio").add_permission(PERMISSION::WRITE));
-    configs.push(Config::new("/proc/sys/vm/dirty_background_ratio").add_permission(PERMISSION::WRITE));
-    configs
+    vec![
+        Config::new("/proc/sys/vm/laptop_mode").add_permission(PERMISSION::WRITE),
+        Config::new("/proc/sys/vm/dirty_writeback_centisecs").add_permission(PERMISSION::WRITE),
+        Config::new("/proc/sys/vm/dirty_expire_centisecs").add_permission(PERMISSION::WRITE),
+        Config::new("/proc/sys/vm/dirty_ratio").add_permission(PERMISSION::WRITE),
+        Config::new("/proc/sys/vm/dirty_background_ratio").add_permission(PERMISSION::WRITE),
+    ]
 }
 
 fn enumerate_fs() -> Vec<Config> {
@@ -227,40 +411,15 @@ fn enumerate_audio() -> Vec<Config> {
 
 fn enumerate_graphics() -> Vec<Config> {
     let mut configs = Vec::new();
-    fn is_cardname(entry: &walkdir::DirEntry) -> bool {
-        if entry.path().to_str().unwrap() == "/sys/class/drm" {
-            return false;
-        }
-        let realpath = if entry.file_type().is_symlink() {
-            let paths = std::fs::read_link(entry.path()).unwrap();
-            std::path::Path::new("/sys/class/drm").join(paths).canonicalize().unwrap()
-        } else if entry.file_type().is_dir() {
-            entry.path().to_path_buf()
-        } else {
-            return false;
-        };
-
-        realpath.file_name()
-             .map(|s| {
-                let re = regex::Regex::new(r"^card\d{1,3}$").unwrap();
-                re.is_match(s.to_str().unwrap())
-             })
-             .unwrap_or(false)
-    }
-
-    for entry in walkdir::WalkDir::new("/sys/class/drm")
-            .max_depth(1)
-            .into_iter()
-            .filter_map(Result::ok)
-            .filter(|e|  (e.file_type().is_dir() || e.file_type().is_symlink()) && is_cardname(e)) {
-                let fullpath = entry.path().to_str().unwrap();
-                configs.push(Config::new(&format!("{}/device/power_dpm_force_performance_level", fullpath)));
-                configs.push(Config::new(&format!("{}/device/power_dpm_state", fullpath)));
-                configs.push(Config::new(&format!("{}/device/power_method", fullpath)));
-                configs.push(Config::new(&format!("{}/device/power_profile", fullpath)));
-                configs.push(Config::new(&format!("{}/gt_min_freq_mhz", fullpath)));
-                configs.push(Config::new(&format!("{}/gt_max_freq_mhz", fullpath)));
-                configs.push(Config::new(&format!("{}/gt_boost_freq_mhz", fullpath)));
+    for card in crate::gpu::enumerate_cards() {
+        let fullpath = crate::utils::logicpath(&card);
+        configs.push(Config::new(&format!("{}/device/power_dpm_force_performance_level", fullpath)));
+        configs.push(Config::new(&format!("{}/device/power_dpm_state", fullpath)));
+        configs.push(Config::new(&format!("{}/device/power_method", fullpath)));
+        configs.push(Config::new(&format!("{}/device/power_profile", fullpath)));
+        configs.push(Config::new(&format!("{}/gt_min_freq_mhz", fullpath)));
+        configs.push(Config::new(&format!("{}/gt_max_freq_mhz", fullpath)));
+        configs.push(Config::new(&format!("{}/gt_boost_freq_mhz", fullpath)));
     }
     configs
 }
@@ -277,27 +436,14 @@ fn enumerate_kernel() -> Vec<Config> {
 
 fn enumerate_power() -> Vec<Config> {
     const ROOTPATH: &str = "/sys/power";
-    let mut configs = Vec::new();
-    configs.push(Config::new_with_handler(&format!("{}/mem_sleep", ROOTPATH), |s| {
-        let start = match  s.find("[") {
-            Some(s) => s + 1,
-            None => 0,
-        };
-        let end = match s.find("]") {
-            Some(e) => e,
-            None => s.len(),
-        };
-
-        s[start..end].to_string()
-    }).add_permission(PERMISSION::WRITE));
-    configs
+    vec![Config::new_with_handler(&format!("{}/mem_sleep", ROOTPATH), selected).add_permission(PERMISSION::WRITE)]
 }
 
 fn enumerate_scsihost() -> Vec<Config> {
     let mut configs = Vec::new();
     const ROOTPATH: &str = "/sys/class/scsi_host";
-    fn is_scsihost_name(entry: &walkdir::DirEntry) -> bool {
-        if entry.path().to_str().unwrap() == "/sys/class/scsi_host" {
+    fn is_scsihost_name(rootpath: &str, entry: &walkdir::DirEntry) -> bool {
+        if entry.path().to_str().unwrap() == rootpath {
             return false;
         }
         entry.file_name()
@@ -308,13 +454,14 @@ fn enumerate_scsihost() -> Vec<Config> {
              })
              .unwrap_or(false)
     }
-    for entry in walkdir::WalkDir::new("/sys/class/scsi_host")
+    let rootpath = crate::utils::rootpath(ROOTPATH);
+    for entry in walkdir::WalkDir::new(&rootpath)
             .max_depth(1)
             .sort_by_file_name()
             .into_iter()
             .filter_map(Result::ok)
-            .filter(|e|  (e.file_type().is_dir() || e.file_type().is_symlink()) && is_scsihost_name(e)) {
-                let fullpath = entry.path().to_str().unwrap();
+            .filter(|e|  (e.file_type().is_dir() || e.file_type().is_symlink()) && is_scsihost_name(&rootpath, e)) {
+                let fullpath = crate::utils::logicpath(entry.path());
                 configs.push(Config::new(&format!("{}/power/control", fullpath)).add_permission(PERMISSION::WRITE));
                 configs.push(Config::new(&format!("{}/link_power_management_policy", fullpath)).add_permission(PERMISSION::WRITE));
     }
@@ -324,14 +471,15 @@ fn enumerate_scsihost() -> Vec<Config> {
 fn enumerate_pci_device() ->  Vec<Config> {
     let mut configs = Vec::new();
     const ROOTPATH: &str = "/sys/bus/pci/devices";
-    for entry in walkdir::WalkDir::new("/sys/bus/pci/devices")
+    let rootpath = crate::utils::rootpath(ROOTPATH);
+    for entry in walkdir::WalkDir::new(&rootpath)
             .max_depth(1)
             .sort_by_file_name()
             .into_iter()
             .filter_map(Result::ok)
             .filter(|e|  e.file_type().is_dir() || e.file_type().is_symlink()) {
-                if entry.path().to_str().unwrap() != "/sys/bus/pci/devices" {
-                    let fullpath = entry.path().to_str().unwrap();
+                if entry.path().to_str().unwrap() != rootpath {
+                    let fullpath = crate::utils::logicpath(entry.path());
                     configs.push(Config::new(&format!("{}/power/control", fullpath)).add_permission(PERMISSION::WRITE));
                 }
     }
@@ -340,69 +488,24 @@ fn enumerate_pci_device() ->  Vec<Config> {
 
 fn enumerate_block_device() -> Vec<Config> {
     let mut configs = Vec::new();
-    for entry in walkdir::WalkDir::new("/sys/block")
-            .max_depth(1)
-            .sort_by_file_name()
-            .into_iter()
-            .filter_map(Result::ok)
-            .filter(|e|  e.file_type().is_dir() || e.file_type().is_symlink()) {
-                if entry.path().to_str().unwrap() != "/sys/block" {
-                    if !entry.file_name().to_str().unwrap().starts_with("loop") {
-                        let fullpath = entry.path().to_str().unwrap();
-                        configs.push(Config::new(&format!("{}/device/power/control", fullpath)).add_permission(PERMISSION::WRITE));
-                        configs.push(Config::new(&format!("{}/device/power/autosuspend_delay_ms", fullpath)).add_permission(PERMISSION::WRITE));
-                        configs.push(Config::new_with_handler(&format!("{}/queue/scheduler", fullpath), |s| {
-                            let start = match  s.find("[") {
-                                Some(s) => s + 1,
-                                None => 0,
-                            };
-                            let end = match s.find("]") {
-                                Some(e) => e,
-                                None => s.len(),
-                            };
-                    
-                            s[start..end].to_string()
-                        }).add_permission(PERMISSION::WRITE));
-                    }
-                }
+    for disk in crate::block::enumerate() {
+        let fullpath = format!("/sys/block/{}", disk);
+        configs.push(Config::new(&format!("{}/device/power/control", fullpath)).add_permission(PERMISSION::WRITE));
+        configs.push(Config::new(&format!("{}/device/power/autosuspend_delay_ms", fullpath)).add_permission(PERMISSION::WRITE));
+        configs.push(Config::new_with_handler(&format!("{}/queue/scheduler", fullpath), selected).add_permission(PERMISSION::WRITE));
     }
     configs
 }
 
 fn enumerate_pcie_aspm() -> Vec<Config> {
-    let mut configs = Vec::new();
     // [default] performance powersave powersupersave
-    configs.push(Config::new_with_handler("/sys/module/pcie_aspm/parameters/policy", |s| {
-        let start = match  s.find("[") {
-            Some(s) => s + 1,
-            None => 0,
-        };
-        let end = match s.find("]") {
-            Some(e) => e,
-            None => s.len(),
-        };
-
-        s[start..end].to_string()
-    }).add_permission(PERMISSION::WRITE));
-    configs
+    vec![Config::new_with_handler("/sys/module/pcie_aspm/parameters/policy", selected).add_permission(PERMISSION::WRITE)]
 }
 
 fn enumerate_net_wakeup() -> Vec<Config> {
     let mut configs = Vec::new();
-
-    for entry in walkdir::WalkDir::new("/sys/class/net")
-            .max_depth(1)
-            .sort_by_file_name()
-            .into_iter()
-            .filter_map(Result::ok)
-            .filter(|e|  e.file_type().is_dir() || e.file_type().is_symlink()) {
-                if entry.path().to_str().unwrap() != "/sys/class/net" {
-                    let name = entry.file_name().to_str().unwrap().to_string();
-                    if name != "lo" && !name.starts_with("docker") {
-                        let fullpath = entry.path().to_str().unwrap();
-                        configs.push(Config::new(&format!("{}/device/power/wakeup", fullpath)).add_permission(PERMISSION::WRITE));
-                    }
-                }
+    for iface in crate::net::enumerate() {
+        configs.push(Config::new(&format!("/sys/class/net/{}/device/power/wakeup", iface)).add_permission(PERMISSION::WRITE));
     }
     configs
 }
@@ -419,17 +522,103 @@ fn enumerate_usb_wakeup() -> Vec<Config> {
              .unwrap_or(false)
     }
 
-    for entry in walkdir::WalkDir::new("/sys/bus/usb/devices")
+    let rootpath = crate::utils::rootpath("/sys/bus/usb/devices");
+    for entry in walkdir::WalkDir::new(&rootpath)
             .max_depth(1)
             .sort_by_file_name()
             .into_iter()
             .filter_map(Result::ok)
             .filter(|e| (e.file_type().is_dir() || e.file_type().is_symlink()) && is_usb_name(e)) {
-                if entry.path().to_str().unwrap() != "/sys/bus/usb/devices" {
-                    let fullpath = entry.path().to_str().unwrap();
+                if entry.path().to_str().unwrap() != rootpath {
+                    let fullpath = crate::utils::logicpath(entry.path());
                     configs.push(Config::new(&format!("{}/power/wakeup", fullpath)).add_permission(PERMISSION::WRITE));
                     configs.push(Config::new(&format!("{}/link_power_management_policy", fullpath)).add_permission(PERMISSION::WRITE));
                 }
     }
     configs
-}
\ No newline at end of file
+}
+
+fn enumerate_battery() -> Vec<Config> {
+    let mut configs = Vec::new();
+    // in %, start must stay below end, see `sort_changes`
+    fn threshold(value: &str, start_path: Option<&str>, end_path: Option<&str>) -> crate::error::Result<()> {
+        let value = match value.trim().parse::<u32>() {
+            Ok(v) if v <= 100 => v,
+            _ => return Err(crate::error::Error::Invalid(format!("threshold {:?} is not in 0-100", value))),
+        };
+        // a driver may only offer one of the two thresholds
+        let (start, end) = match (start_path, end_path) {
+            (Some(path), None) => (read_threshold(path).ok(), Some(value)),
+            (None, Some(path)) => (Some(value), read_threshold(path).ok()),
+            _ => (None, None),
+        };
+        match (start, end) {
+            (Some(start), Some(end)) if start >= end => {
+                Err(crate::error::Error::Invalid(format!("start threshold {} is not below end threshold {}", start, end)))
+            },
+            _ => Ok(()),
+        }
+    }
+
+    for battery in crate::battery::enumerate() {
+        let fullpath = format!("/sys/class/power_supply/{}", battery.name);
+        let start = format!("{}/charge_control_start_threshold", fullpath);
+        let end = format!("{}/charge_control_end_threshold", fullpath);
+        let (start_path, end_path) = (start.clone(), end.clone());
+        configs.push(Config::new(&start).add_permission(PERMISSION::WRITE)
+            .with_validator(move |v| threshold(v, None, Some(&end_path))));
+        configs.push(Config::new(&end).add_permission(PERMISSION::WRITE)
+            .with_validator(move |v| threshold(v, Some(&start_path), None)));
+        // [auto] inhibit-charge force-discharge
+        configs.push(Config::new_with_handler(&format!("{}/charge_behaviour", fullpath), selected).add_permission(PERMISSION::WRITE));
+    }
+    configs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // BAT0 thresholds are start 40, end 80 in the fixture
+    fn changes(start: &str, end: &str) -> Vec<Config> {
+        crate::utils::set_root(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/laptop"));
+        let node = |attr: &str| format!("/sys/class/power_supply/BAT0/charge_control_{}_threshold", attr);
+        let mut start_config = Config::new(&node("start"));
+        start_config.value = Some(start.to_string());
+        let mut end_config = Config::new(&node("end"));
+        end_config.value = Some(end.to_string());
+        let mut other = Config::new("/sys/power/mem_sleep");
+        other.value = Some("deep".to_string());
+        vec![start_config, other, end_config]
+    }
+
+    fn order(changes: &[Config]) -> Vec<&str> {
+        changes.iter().map(|c| c.node.rsplit('/').next().unwrap()).collect()
+    }
+
+    #[test]
+    fn sort_changes_raises_end_first() {
+        let mut changes = changes("60", "90");
+        sort_changes(&mut changes);
+        assert_eq!(order(&changes), vec!["charge_control_end_threshold", "mem_sleep", "charge_control_start_threshold"]);
+    }
+
+    #[test]
+    fn sort_changes_lowers_start_first() {
+        let mut changes = changes("20", "30");
+        sort_changes(&mut changes);
+        assert_eq!(order(&changes), vec!["charge_control_start_threshold", "mem_sleep", "charge_control_end_threshold"]);
+
+        // an end listed first moves behind the start
+        let mut changes: Vec<Config> = changes.into_iter().rev().collect();
+        sort_changes(&mut changes);
+        assert_eq!(order(&changes), vec!["charge_control_start_threshold", "mem_sleep", "charge_control_end_threshold"]);
+    }
+
+    #[test]
+    fn selected_value() {
+        assert_eq!(selected("[none] mq-deadline"), "none");
+        assert_eq!(selected("s2idle [deep]"), "deep");
+        assert_eq!(selected("powersave"), "powersave");
+    }
+}
@@ -4,7 +4,10 @@ use std::io::BufRead;
 
 static ROOTPATH: &str = "/sys/devices/system/cpu";
 
-#[derive(Clone, Debug)]
+/// A logical cpu as listed in `/proc/cpuinfo`, with its topology and
+/// cpufreq policy read from sysfs.
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Clone, Debug, Default)]
 pub struct CPU {
     number: u32,
     vendor: String,
@@ -19,48 +22,120 @@ pub struct CPU {
 }
 
 impl CPU {
-    pub fn default() -> CPU {
-        CPU{number: 0,vendor: "".to_string(),
-            family: "".to_string(),
-            model: "".to_string(),
-            core_id: 0,
-            physical_package_id: 0,
-            scaling_driver: "".to_string(),
-            scaling_governor: "".to_string(), 
-            scaling_min_freq: 0, 
-            scaling_max_freq: 0}
+    /// Logical cpu number, the `N` of `cpuN`.
+    pub fn number(&self) -> u32 {
+        self.number
     }
+
+    pub fn vendor(&self) -> &str {
+        &self.vendor
+    }
+
+    pub fn family(&self) -> &str {
+        &self.family
+    }
+
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    pub fn core_id(&self) -> u32 {
+        self.core_id
+    }
+
+    pub fn physical_package_id(&self) -> u32 {
+        self.physical_package_id
+    }
+
+    pub fn scaling_driver(&self) -> &str {
+        &self.scaling_driver
+    }
+
+    /// Governor at enumeration time, see `config` for the live value.
+    pub fn scaling_governor(&self) -> &str {
+        &self.scaling_governor
+    }
+
+    /// Minimum scaling frequency in kHz.
+    pub fn scaling_min_freq(&self) -> u32 {
+        self.scaling_min_freq
+    }
+
+    /// Maximum scaling frequency in kHz.
+    pub fn scaling_max_freq(&self) -> u32 {
+        self.scaling_max_freq
+    }
+
+    /// `package:core:number`, used as csv column name.
     pub fn tag(&self) -> String {
-        return format!("{}:{}:{}", self.physical_package_id, self.core_id, self.number).to_string();
+        format!("{}:{}:{}", self.physical_package_id, self.core_id, self.number)
     }
 
-    pub fn freq(&self) -> u32 {
-        if let Ok(freq_val) = crate::utils::read_line(&format!("{}/cpu{}/cpufreq/scaling_cur_freq", ROOTPATH, self.number)) {
-            if let Ok(val) = freq_val.parse::<u32>() {
-                return val
-            } else {
-                return 0;
-            }
-        }
-        return 0
+    /// Current frequency in kHz from `scaling_cur_freq`.
+    pub fn freq(&self) -> crate::error::Result<u32> {
+        crate::utils::read_value(&format!("{}/cpu{}/cpufreq/scaling_cur_freq", crate::utils::rootpath(ROOTPATH), self.number))
     }
-}
 
-pub fn new_cpu(number: u32, vendor: &str, family: &str, model: &str) -> std::io::Result<CPU> {
-    let core_str = crate::utils::read_line(&format!("{}/cpu{}/topology/core_id", ROOTPATH, number))?;
-    let core_id = core_str.parse::<u32>().map_err(|e| {std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string())})?;
+    /// Every `cpuidle/stateM` ordered by `M`, empty without cpuidle.
+    pub fn idle_states(&self) -> crate::error::Result<Vec<IdleState>> {
+        let mut states = Vec::new();
+        let path = format!("{}/cpu{}/cpuidle", crate::utils::rootpath(ROOTPATH), self.number);
+        let re = regex::Regex::new(r"^state(\d{1,3})$").unwrap();
+        for entry in walkdir::WalkDir::new(&path)
+                .min_depth(1)
+                .max_depth(1)
+                .into_iter()
+                .filter_map(Result::ok)
+                .filter(|e|  e.file_type().is_dir()) {
+                    let file_name = entry.file_name().to_string_lossy().to_string();
+                    let index = match re.captures(&file_name).and_then(|caps| caps[1].parse::<u32>().ok()) {
+                        Some(index) => index,
+                        None => continue,
+                    };
+                    let state = entry.path().to_string_lossy().to_string();
+                    states.push(IdleState {
+                        index,
+                        name: crate::utils::read_value(&format!("{}/name", state))?,
+                        time: crate::utils::read_value(&format!("{}/time", state))?,
+                        usage: crate::utils::read_value(&format!("{}/usage", state))?,
+                    });
+        }
 
-    let physical_package = crate::utils::read_line(&format!("{}/cpu{}/topology/physical_package_id", ROOTPATH, number))?;
-    let physical_package_id = physical_package.parse::<u32>().map_err(|e| {std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string())})?;
+        // state10 sorts before state2 by file name
+        states.sort_by_key(|s| s.index);
+        Ok(states)
+    }
+}
 
-    let scaling_driver = crate::utils::read_line(&format!("{}/cpu{}/cpufreq/scaling_driver", ROOTPATH, number))?;
-    let scaling_governor = crate::utils::read_line(&format!("{}/cpu{}/cpufreq/scaling_governor", ROOTPATH, number))?;
+/// One cpuidle state of a cpu with its counters.
+#[derive(Clone, Debug, Default)]
+pub struct IdleState {
+    /// The `M` of `stateM`.
+    pub index: u32,
+    /// State `name`, e.g. `POLL`, `C1E` or `C6`.
+    pub name: String,
+    /// Total residency in µs.
+    pub time: u64,
+    /// Number of times the state was entered.
+    pub usage: u64,
+}
 
-    let scaling_min_freq_str = crate::utils::read_line(&format!("{}/cpu{}/cpufreq/scaling_min_freq", ROOTPATH, number))?;
-    let scaling_min_freq = scaling_min_freq_str.parse::<u32>().map_err(|e| {std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string())})?;
+/// Read topology and cpufreq policy of `cpuN`.
+pub fn new_cpu(number: u32, vendor: &str, family: &str, model: &str) -> crate::error::Result<CPU> {
+    let mut cpu = new_cpu_topology(number, vendor, family, model)?;
+    let cpupath = format!("{}/cpu{}", crate::utils::rootpath(ROOTPATH), number);
+    cpu.scaling_driver = crate::utils::read_value(&format!("{}/cpufreq/scaling_driver", cpupath))?;
+    cpu.scaling_governor = crate::utils::read_value(&format!("{}/cpufreq/scaling_governor", cpupath))?;
+    cpu.scaling_min_freq = crate::utils::read_value(&format!("{}/cpufreq/scaling_min_freq", cpupath))?;
+    cpu.scaling_max_freq = crate::utils::read_value(&format!("{}/cpufreq/scaling_max_freq", cpupath))?;
+    Ok(cpu)
+}
 
-    let scaling_max_freq_str = crate::utils::read_line(&format!("{}/cpu{}/cpufreq/scaling_max_freq", ROOTPATH, number))?;
-    let scaling_max_freq = scaling_max_freq_str.parse::<u32>().map_err(|e| {std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string())})?;
+/// Read topology of `cpuN`, leaving its cpufreq policy empty.
+pub fn new_cpu_topology(number: u32, vendor: &str, family: &str, model: &str) -> crate::error::Result<CPU> {
+    let cpupath = format!("{}/cpu{}", crate::utils::rootpath(ROOTPATH), number);
+    let core_id = crate::utils::read_value(&format!("{}/topology/core_id", cpupath))?;
+    let physical_package_id = crate::utils::read_value(&format!("{}/topology/physical_package_id", cpupath))?;
 
     Ok(CPU{
         number,
@@ -69,67 +144,141 @@ pub fn new_cpu(number: u32, vendor: &str, family: &str, model: &str) -> std::io:
         model: model.to_string(),
         core_id,
         physical_package_id,
-        scaling_driver,
-        scaling_governor,
-        scaling_min_freq,
-        scaling_max_freq,
+        ..Default::default()
     })
 }
 
+/// Enumerate every cpu in `/proc/cpuinfo` that has cpufreq support.
 pub fn enumerate() -> Vec<CPU> {
+    enumerate_with(new_cpu)
+}
+
+/// Enumerate every cpu in `/proc/cpuinfo`, with the cpufreq policy of
+/// those that have one.
+pub fn enumerate_all() -> Vec<CPU> {
+    enumerate_with(|number, vendor, family, model| {
+        new_cpu(number, vendor, family, model).or_else(|_| new_cpu_topology(number, vendor, family, model))
+    })
+}
+
+fn enumerate_with(new: impl Fn(u32, &str, &str, &str) -> crate::error::Result<CPU>) -> Vec<CPU> {
     let mut cpus = Vec::<CPU>::new();
-    if let Ok(input) = File::open("/proc/cpuinfo") {
+    if let Ok(input) = File::open(crate::utils::rootpath("/proc/cpuinfo")) {
         let reader = std::io::BufReader::new(input);
 
-        //let mut cpu = CPU::default();
         let mut vendor = String::new();
-        let mut number = 0;
+        let mut number = None;
         let mut family = String::new();
         let mut model = String::new();
-        let mut found = false;
-        for line in reader.lines() {
-            let line = line.unwrap().trim().to_string();
-            
+        for line in reader.lines().map_while(Result::ok) {
+            let line = line.trim();
+            let value = match line.split_once(':') {
+                Some((_, v)) => v.trim(),
+                None => "",
+            };
+
             if line.starts_with("vendor_id\t") {
-                //
-                let v: Vec<&str> = line.split(":").collect();
-                if v.len() > 1 {
-                    vendor = v[1].trim().to_string();
-                }
+                vendor = value.to_string();
             } else if line.starts_with("processor\t") {
-                let v: Vec<&str> = line.split(":").collect();
-                if v.len() > 1 {
-                    number = v[1].trim().parse::<u32>().unwrap();
-                    found = true;
-                }
+                number = value.parse::<u32>().ok();
             } else if line.starts_with("cpu family\t") {
-                let v: Vec<&str> = line.split(":").collect();
-                if v.len() > 1 {
-                    family = v[1].trim().to_string();
-                }
-
+                family = value.to_string();
             } else if line.starts_with("model\t") {
-                let v: Vec<&str> = line.split(":").collect();
-                if v.len() > 1 {
-                    model = v[1].trim().to_string();
-                }
+                model = value.to_string();
             }
 
-            if line.is_empty() && found {
-                if let Ok(cpu) = new_cpu(number, &vendor, &family, &model) {
-                    cpus.push(cpu);
+            if line.is_empty() {
+                if let Some(n) = number.take() {
+                    if let Ok(cpu) = new(n, &vendor, &family, &model) {
+                        cpus.push(cpu);
+                    }
                 }
-                found = false;
             }
         }
 
-        if found {
-            if let Ok(cpu) = new_cpu(number, &vendor, &family, &model) {
+        if let Some(n) = number {
+            if let Ok(cpu) = new(n, &vendor, &family, &model) {
                 cpus.push(cpu);
             }
-            found = false;
         }
     }
 
-    return cpus;
-}
\ No newline at end of file
+    cpus
+}
+
+/// Jiffies a cpu spent in each state, one `cpuN` line of `/proc/stat`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CpuTimes {
+    pub user: u64,
+    pub nice: u64,
+    pub system: u64,
+    pub idle: u64,
+    pub iowait: u64,
+    pub irq: u64,
+    pub softirq: u64,
+    pub steal: u64,
+}
+
+impl CpuTimes {
+    fn parse(fields: &[&str]) -> Option<Self> {
+        let val = |i: usize| fields.get(i).and_then(|v| v.parse::<u64>().ok());
+        Some(CpuTimes {
+            user: val(0)?,
+            nice: val(1)?,
+            system: val(2)?,
+            idle: val(3)?,
+            iowait: val(4)?,
+            irq: val(5)?,
+            softirq: val(6)?,
+            // steal was added in 2.6.11
+            steal: val(7).unwrap_or_default(),
+        })
+    }
+
+    /// Sum of every state, guest time is already part of `user`.
+    pub fn total(&self) -> u64 {
+        self.user + self.nice + self.system + self.idle + self.iowait + self.irq + self.softirq + self.steal
+    }
+}
+
+/// The cpu and `ctxt` lines of `/proc/stat`.
+#[derive(Clone, Debug, Default)]
+pub struct Stat {
+    /// The aggregate `cpu` line.
+    pub total: CpuTimes,
+    /// `cpuN` lines keyed by `N`.
+    pub cpus: std::collections::HashMap<u32, CpuTimes>,
+    /// Context switches since boot.
+    pub ctxt: u64,
+}
+
+/// Read `/proc/stat`.
+pub fn stat() -> crate::error::Result<Stat> {
+    let path = crate::utils::rootpath("/proc/stat");
+    let data = crate::utils::read_all_line(&path).map_err(|e| crate::error::Error::from_io(&path, e))?;
+
+    let mut stat = Stat::default();
+    for line in data.lines() {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let name = match fields.first() {
+            Some(name) if name.starts_with("cpu") => *name,
+            Some(&"ctxt") => {
+                match fields.get(1).and_then(|v| v.parse::<u64>().ok()) {
+                    Some(ctxt) => stat.ctxt = ctxt,
+                    None => return Err(crate::error::Error::Parse { path, value: line.to_string() }),
+                }
+                continue;
+            },
+            _ => continue,
+        };
+        let times = match CpuTimes::parse(&fields[1..]) {
+            Some(times) => times,
+            None => return Err(crate::error::Error::Parse { path, value: line.to_string() }),
+        };
+        match name[3..].parse::<u32>() {
+            Ok(number) => { stat.cpus.insert(number, times); },
+            Err(_) => stat.total = times,
+        }
+    }
+    Ok(stat)
+}
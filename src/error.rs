@@ -0,0 +1,81 @@
+use std::fmt;
+
+/// Crate-wide error, keeping "couldn't read" apart from a real zero reading.
+#[derive(Debug)]
+pub enum Error {
+    /// The node does not exist, e.g. the driver doesn't expose it.
+    NotFound(String),
+    /// The node exists but is not readable/writeable by this user.
+    PermissionDenied(String),
+    /// The node was read but its content is not the expected format.
+    Parse { path: String, value: String },
+    /// The device behind the node went away (ENODEV/ENXIO), e.g. unplugged.
+    DeviceGone(String),
+    /// Any other io error.
+    Io { path: String, source: std::io::Error },
+    /// Malformed input that is not tied to a node, e.g. a config file.
+    Invalid(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+// errno values sysfs returns for a removed or powered off device
+const ENXIO: i32 = 6;
+const ENODEV: i32 = 19;
+
+impl Error {
+    /// Classify an io error raised while accessing `path`.
+    pub fn from_io(path: &str, e: std::io::Error) -> Self {
+        if let Some(ENXIO | ENODEV) = e.raw_os_error() {
+            return Error::DeviceGone(path.to_string());
+        }
+        match e.kind() {
+            std::io::ErrorKind::NotFound => Error::NotFound(path.to_string()),
+            std::io::ErrorKind::PermissionDenied => Error::PermissionDenied(path.to_string()),
+            _ => Error::Io { path: path.to_string(), source: e },
+        }
+    }
+
+    /// Classify a csv error raised while reading or writing `path`.
+    pub fn from_csv(path: &str, e: csv::Error) -> Self {
+        let value = e.to_string();
+        match e.into_kind() {
+            csv::ErrorKind::Io(e) => Error::from_io(path, e),
+            _ => Error::Parse { path: path.to_string(), value },
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::NotFound(path) => write!(f, "{}: no such node", path),
+            Error::PermissionDenied(path) => write!(f, "{}: permission denied", path),
+            Error::Parse { path, value } => write!(f, "{}: can't parse {:?}", path, value),
+            Error::DeviceGone(path) => write!(f, "{}: device gone", path),
+            Error::Io { path, source } => write!(f, "{}: {}", path, source),
+            Error::Invalid(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<Error> for std::io::Error {
+    fn from(e: Error) -> Self {
+        let kind = match &e {
+            Error::NotFound(_) => std::io::ErrorKind::NotFound,
+            Error::PermissionDenied(_) => std::io::ErrorKind::PermissionDenied,
+            Error::Parse { .. } | Error::Invalid(_) => std::io::ErrorKind::InvalidData,
+            Error::DeviceGone(_) | Error::Io { .. } => std::io::ErrorKind::Other,
+        };
+        std::io::Error::new(kind, e)
+    }
+}
@@ -0,0 +1,257 @@
+// time to empty/full from a sliding window of battery samples
+use std::collections::VecDeque;
+
+/// Direction the batteries are going, from their `status`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Charging,
+    Discharging,
+}
+
+/// One reading of every battery, summed.
+#[derive(Clone, Copy, Debug)]
+pub struct Sample {
+    /// Seconds since the estimator started.
+    pub secs: f64,
+    /// Remaining energy in Wh.
+    pub energy_wh: f64,
+    /// Energy when full in Wh.
+    pub full_wh: f64,
+    /// Power draw in W, `None` if no battery reports it.
+    pub power_w: Option<f64>,
+}
+
+/// Remaining time until empty or full, in seconds.
+#[derive(Clone, Copy, Debug)]
+pub struct Estimate {
+    pub direction: Direction,
+    pub secs: f64,
+    /// Bounds at one standard deviation of the draw, `high` is `None`
+    /// when the draw may be zero.
+    pub low: f64,
+    pub high: Option<f64>,
+}
+
+/// Smooths the power draw over the last `window` seconds.
+///
+/// The draw comes from `power_now` when the batteries report it, else
+/// from the slope of the remaining energy over the window.
+pub struct Estimator {
+    window: f64,
+    direction: Option<Direction>,
+    samples: VecDeque<Sample>,
+}
+
+impl Estimator {
+    pub fn new(window: f64) -> Self {
+        Self { window, direction: None, samples: VecDeque::new() }
+    }
+
+    /// Add a sample, a change of direction starts the window over.
+    pub fn push(&mut self, direction: Option<Direction>, sample: Sample) {
+        if direction != self.direction {
+            self.samples.clear();
+            self.direction = direction;
+        }
+        self.samples.push_back(sample);
+        while self.samples.front().is_some_and(|s| sample.secs - s.secs > self.window) {
+            self.samples.pop_front();
+        }
+    }
+
+    // mean and standard deviation of the draw in W
+    fn rate(&self) -> Option<(f64, f64)> {
+        let powers: Vec<f64> = self.samples.iter().filter_map(|s| s.power_w).collect();
+        if !powers.is_empty() {
+            let mean = powers.iter().sum::<f64>() / powers.len() as f64;
+            let var = powers.iter().map(|p| (p - mean).powi(2)).sum::<f64>() / powers.len() as f64;
+            return Some((mean, var.sqrt()));
+        }
+
+        // least squares slope of the energy, in Wh/s
+        let n = self.samples.len() as f64;
+        if n < 2.0 {
+            return None;
+        }
+        let mean_t = self.samples.iter().map(|s| s.secs).sum::<f64>() / n;
+        let mean_e = self.samples.iter().map(|s| s.energy_wh).sum::<f64>() / n;
+        let cov = self.samples.iter().map(|s| (s.secs - mean_t) * (s.energy_wh - mean_e)).sum::<f64>();
+        let var_t = self.samples.iter().map(|s| (s.secs - mean_t).powi(2)).sum::<f64>();
+        if var_t == 0.0 {
+            return None;
+        }
+        let slope = cov / var_t;
+        let residual = self.samples.iter()
+            .map(|s| (s.energy_wh - mean_e - slope * (s.secs - mean_t)).powi(2))
+            .sum::<f64>() / n;
+        // standard error of the slope
+        Some(((slope * 3600.0).abs(), residual.sqrt() / var_t.sqrt() * 3600.0))
+    }
+
+    /// Current estimate, `None` while idle, full or without enough samples.
+    pub fn estimate(&self) -> Option<Estimate> {
+        let direction = self.direction?;
+        let last = self.samples.back()?;
+        let (power, sd) = self.rate()?;
+        if power <= 0.0 {
+            return None;
+        }
+        let remaining_wh = match direction {
+            Direction::Discharging => last.energy_wh,
+            Direction::Charging => (last.full_wh - last.energy_wh).max(0.0),
+        };
+        let secs = |w: f64| remaining_wh / w * 3600.0;
+        Some(Estimate {
+            direction,
+            secs: secs(power),
+            low: secs(power + sd),
+            high: if power - sd > 0.0 { Some(secs(power - sd)) } else { None },
+        })
+    }
+}
+
+/// Read every battery into one sample and its direction, `Charging` if
+/// any battery charges.
+pub fn sample(batterys: &[crate::battery::Battery], secs: f64) -> crate::error::Result<(Option<Direction>, Sample)> {
+    let mut energy_wh = 0.0;
+    let mut full_wh = 0.0;
+    let mut power_w = None;
+    let mut direction = None;
+    for battery in batterys {
+        let (now, full) = battery.energy()?;
+        energy_wh += now;
+        full_wh += full;
+        if let Ok(uw) = battery.power_now() {
+            *power_w.get_or_insert(0.0) += uw as f64 / 1e6;
+        }
+        match battery.status().as_deref() {
+            Ok("Charging") => direction = Some(Direction::Charging),
+            Ok("Discharging") if direction.is_none() => direction = Some(Direction::Discharging),
+            _ => {},
+        }
+    }
+    Ok((direction, Sample { secs, energy_wh, full_wh, power_w }))
+}
+
+/// Print the charge and the time to empty or full of the batteries every
+/// `interval` ms, smoothed over `window` seconds, until interrupted.
+/// While the batteries neither charge nor discharge, e.g. when full, only
+/// the status is printed until that changes.
+pub fn watch(interval: u64, window: u64) -> crate::error::Result<()> {
+    let bats = crate::battery::enumerate();
+    if bats.is_empty() {
+        return Err(crate::error::Error::NotFound("no battery found".to_string()));
+    }
+    let mut estimator = Estimator::new(window as f64);
+    let mut clock = crate::collector::Clock::new(std::time::Duration::from_millis(interval));
+    let mut last_direction = None;
+    loop {
+        let ts = clock.now();
+        let secs = ts.elapsed as f64 / 1000.0;
+        match sample(&bats, secs) {
+            Ok((direction, sample)) => {
+                if last_direction != Some(direction) {
+                    println!("status: {}", match direction {
+                        Some(Direction::Charging) => "Charging",
+                        Some(Direction::Discharging) => "Discharging",
+                        None => "full or not charging, waiting until the batteries charge or discharge",
+                    });
+                    last_direction = Some(direction);
+                }
+                estimator.push(direction, sample);
+                if direction.is_none() {
+                    clock.wait();
+                    continue;
+                }
+                let time = crate::utils::utc_string(ts.millis / 1000);
+                let percent = if sample.full_wh > 0.0 { sample.energy_wh * 100.0 / sample.full_wh } else { 0.0 };
+                let power = sample.power_w.map(|w| format!("{:.2} W", w)).unwrap_or_else(|| "- W".to_string());
+                match estimator.estimate() {
+                    Some(e) => println!("{} {:.1}% {} time to {}: {} ({} - {})",
+                        time, percent, power,
+                        if e.direction == Direction::Charging { "full" } else { "empty" },
+                        crate::utils::hms_string(e.secs), crate::utils::hms_string(e.low),
+                        e.high.map(crate::utils::hms_string).unwrap_or_else(|| "unknown".to_string())),
+                    None => println!("{} {:.1}% {} estimating...", time, percent, power),
+                }
+            },
+            Err(e) => println!("WARNING: {}", e),
+        }
+        clock.wait();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(secs: f64, energy_wh: f64, power_w: Option<f64>) -> Sample {
+        Sample { secs, energy_wh, full_wh: 50.0, power_w }
+    }
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-6
+    }
+
+    #[test]
+    fn slope_without_power_now() {
+        let mut estimator = Estimator::new(300.0);
+        estimator.push(Some(Direction::Discharging), sample(0.0, 10.0, None));
+        assert!(estimator.estimate().is_none(), "one sample has no slope");
+        // 0.01 Wh every 36 s is 1 W
+        for (i, secs) in [36.0, 72.0, 108.0].iter().enumerate() {
+            estimator.push(Some(Direction::Discharging), sample(*secs, 10.0 - 0.01 * (i + 1) as f64, None));
+        }
+        let e = estimator.estimate().unwrap();
+        assert_eq!(e.direction, Direction::Discharging);
+        assert!(close(e.secs, 9.97 * 3600.0));
+        // a straight line has no spread
+        assert!(close(e.low, e.secs));
+        assert!(close(e.high.unwrap(), e.secs));
+    }
+
+    #[test]
+    fn direction_change_starts_over() {
+        let mut estimator = Estimator::new(300.0);
+        for secs in [0.0, 10.0, 20.0] {
+            estimator.push(Some(Direction::Discharging), sample(secs, 10.0, Some(10.0)));
+        }
+        estimator.push(Some(Direction::Charging), sample(30.0, 20.0, Some(20.0)));
+        let e = estimator.estimate().unwrap();
+        assert_eq!(e.direction, Direction::Charging);
+        // 30 Wh to full at 20 W, the 10 W samples are gone
+        assert!(close(e.secs, 5400.0));
+        assert!(close(e.low, e.secs));
+
+        estimator.push(None, sample(40.0, 50.0, Some(0.0)));
+        assert!(estimator.estimate().is_none(), "no estimate while full");
+    }
+
+    #[test]
+    fn window_drops_old_samples() {
+        let mut estimator = Estimator::new(100.0);
+        estimator.push(Some(Direction::Discharging), sample(0.0, 10.0, Some(10.0)));
+        estimator.push(Some(Direction::Discharging), sample(150.0, 10.0, Some(20.0)));
+        assert!(close(estimator.estimate().unwrap().secs, 1800.0));
+    }
+
+    #[test]
+    fn band_from_standard_deviation() {
+        let mut estimator = Estimator::new(300.0);
+        estimator.push(Some(Direction::Discharging), sample(0.0, 10.0, Some(8.0)));
+        estimator.push(Some(Direction::Discharging), sample(10.0, 10.0, Some(12.0)));
+        // 10 W +- 2 W
+        let e = estimator.estimate().unwrap();
+        assert!(close(e.secs, 3600.0));
+        assert!(close(e.low, 3000.0));
+        assert!(close(e.high.unwrap(), 4500.0));
+
+        // the draw may be zero, so there's no upper bound
+        let mut estimator = Estimator::new(300.0);
+        estimator.push(Some(Direction::Discharging), sample(0.0, 10.0, Some(0.0)));
+        estimator.push(Some(Direction::Discharging), sample(10.0, 10.0, Some(20.0)));
+        let e = estimator.estimate().unwrap();
+        assert!(close(e.secs, 3600.0));
+        assert!(e.high.is_none());
+    }
+}
@@ -0,0 +1,136 @@
+// /sys/class/drm/cardN
+static ROOTPATH: &str = "/sys/class/drm";
+
+/// Driver family of a card, deciding which counters it exposes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GpuKind {
+    /// `i915` or `xe`.
+    Intel,
+    /// `amdgpu`.
+    Amd,
+    Other,
+}
+
+/// A DRM card under `/sys/class/drm`.
+#[derive(Clone, Debug)]
+pub struct Gpu {
+    /// `cardN` node name.
+    pub card: String,
+    /// Kernel driver bound to the card's device, e.g. `i915`.
+    pub driver: String,
+    pub kind: GpuKind,
+}
+
+impl Gpu {
+    /// Read the driver of the card at `path`.
+    pub fn new(path: &str) -> crate::error::Result<Gpu> {
+        let card = match std::path::Path::new(path).file_name() {
+            Some(n) => n.to_string_lossy().to_string(),
+            None => return Err(crate::error::Error::NotFound(path.to_string())),
+        };
+        let driver_path = format!("{}/device/driver", path);
+        let driver = std::fs::canonicalize(&driver_path)
+            .map_err(|e| crate::error::Error::from_io(&driver_path, e))?
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let kind = match driver.as_str() {
+            "i915" | "xe" => GpuKind::Intel,
+            "amdgpu" => GpuKind::Amd,
+            _ => GpuKind::Other,
+        };
+
+        Ok(Gpu{
+            card,
+            driver,
+            kind,
+        })
+    }
+
+    fn path(&self) -> String {
+        format!("{}/{}", crate::utils::rootpath(ROOTPATH), self.card)
+    }
+
+    // i915 exposes the gt attributes on the card, xe per tile and gt
+    fn read_intel<T: std::str::FromStr>(&self, i915: &str, xe: &str) -> crate::error::Result<T> {
+        let attr = if self.driver == "xe" { xe } else { i915 };
+        crate::utils::read_value(&format!("{}/{}", self.path(), attr))
+    }
+
+    /// Frequency the driver requested in MHz, Intel only.
+    pub fn cur_freq(&self) -> crate::error::Result<u32> {
+        self.read_intel("gt_cur_freq_mhz", "device/tile0/gt0/freq0/cur_freq")
+    }
+
+    /// Frequency the hardware actually runs at in MHz, Intel only.
+    pub fn act_freq(&self) -> crate::error::Result<u32> {
+        self.read_intel("gt_act_freq_mhz", "device/tile0/gt0/freq0/act_freq")
+    }
+
+    /// Total time spent in RC6 in ms, Intel only.
+    pub fn rc6_residency_ms(&self) -> crate::error::Result<u64> {
+        self.read_intel("power/rc6_residency_ms", "device/tile0/gt0/gtidle/idle_residency_ms")
+    }
+
+    /// GPU load in %, amdgpu only.
+    pub fn busy_percent(&self) -> crate::error::Result<u32> {
+        crate::utils::read_value(&format!("{}/device/gpu_busy_percent", self.path()))
+    }
+
+    /// Current shader clock in MHz, the `*` level of `pp_dpm_sclk`, amdgpu only.
+    pub fn sclk(&self) -> crate::error::Result<u32> {
+        let path = format!("{}/device/pp_dpm_sclk", self.path());
+        let data = crate::utils::read_all_line(&path).map_err(|e| crate::error::Error::from_io(&path, e))?;
+        // 0: 500Mhz
+        // 1: 1800Mhz *
+        data.lines()
+            .find(|line| line.trim_end().ends_with('*'))
+            .and_then(|line| line.split_whitespace().nth(1))
+            .and_then(|mhz| mhz.to_lowercase().strip_suffix("mhz").and_then(|v| v.parse::<u32>().ok()))
+            .ok_or(crate::error::Error::Parse { path, value: data.trim().to_string() })
+    }
+}
+
+fn is_cardname(rootpath: &str, entry: &walkdir::DirEntry) -> bool {
+    if entry.path().to_str().unwrap() == rootpath {
+        return false;
+    }
+    let realpath = if entry.file_type().is_symlink() {
+        match std::fs::read_link(entry.path()).and_then(|p| std::path::Path::new(rootpath).join(p).canonicalize()) {
+            Ok(p) => p,
+            Err(_) => return false,
+        }
+    } else if entry.file_type().is_dir() {
+        entry.path().to_path_buf()
+    } else {
+        return false;
+    };
+
+    realpath.file_name()
+         .map(|s| {
+            let re = regex::Regex::new(r"^card\d{1,3}$").unwrap();
+            re.is_match(&s.to_string_lossy())
+         })
+         .unwrap_or(false)
+}
+
+/// Paths of the `cardN` nodes, connectors like `card0-eDP-1` excluded.
+pub fn enumerate_cards() -> Vec<std::path::PathBuf> {
+    let rootpath = crate::utils::rootpath(ROOTPATH);
+    walkdir::WalkDir::new(&rootpath)
+        .max_depth(1)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|e|  (e.file_type().is_dir() || e.file_type().is_symlink()) && is_cardname(&rootpath, e))
+        .map(|e| e.path().to_path_buf())
+        .collect()
+}
+
+/// Enumerate every card with a readable driver.
+pub fn enumerate() -> Vec<Gpu> {
+    enumerate_cards()
+        .iter()
+        .filter_map(|path| Gpu::new(&path.to_string_lossy()).ok())
+        .collect()
+}
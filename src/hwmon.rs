@@ -3,101 +3,325 @@ use std::collections::HashMap;
 
 static ROOTPATH: &str = "/sys/class/hwmon";
 
+/// A hwmon chip under `/sys/class/hwmon`.
 #[derive(Clone, Debug)]
 pub struct Hwmon {
+    /// `hwmonN` node name, renumbered by the kernel between boots.
     pub node: String,
+    /// Chip `name`, e.g. `coretemp` or `nvme`.
     pub name: String,
+    /// Stable chip identity, the path of its device below `/sys/devices`,
+    /// e.g. `platform/coretemp.0` or `pci0000:00/0000:00:01.0/0000:01:00.0`.
+    /// [`enumerate`] appends `#2`, `#3`, ... to chips sharing a device.
+    pub id: String,
 }
 
 impl Hwmon {
-    pub fn new(path: &str) -> std::io::Result<Hwmon> {
-        let path = std::path::Path::new(path);
-        let node = path.file_name().unwrap().to_str().unwrap().to_string();
-        let name = crate::utils::read_line(&path.join("name").to_str().unwrap().to_string())?;
+    /// Read the chip `name` of the hwmon node at `path`.
+    pub fn new(path: &str) -> crate::error::Result<Hwmon> {
+        let node = match std::path::Path::new(path).file_name() {
+            Some(n) => n.to_string_lossy().to_string(),
+            None => return Err(crate::error::Error::NotFound(path.to_string())),
+        };
+        let name: String = crate::utils::read_value(&format!("{}/name", path))?;
+        let id = device_path(path, &name).unwrap_or_else(|| name.clone());
 
         Ok(Hwmon{
             node,
             name,
+            id,
         })
     }
 
-    pub fn fans(&self) -> HashMap<String, u32> {
-        let mut node_vals :HashMap<String, u32>= HashMap::new();
-        for entry in walkdir::WalkDir::new(format!("{}/{}", ROOTPATH, self.node))
+    fn path(&self) -> String {
+        format!("{}/{}", crate::utils::rootpath(ROOTPATH), self.node)
+    }
+
+    /// Every sensor of the chip, ordered by class and index.
+    pub fn sensors(&self) -> Vec<Sensor> {
+        let mut sensors = Vec::new();
+        let path = self.path();
+        let re = regex::Regex::new(r"^(?<class>[a-z]+)(?<num>\d{1,3})(?<attr>_input|_average)?$").unwrap();
+        for entry in walkdir::WalkDir::new(&path)
                 .sort_by_file_name()
                 .max_depth(1)
                 .into_iter()
                 .filter_map(Result::ok)
-                .filter(|e|  (e.file_type().is_file() || e.file_type().is_symlink())) {
-                    let re = regex::Regex::new(r"^fan\d{1,3}_input$").unwrap();
-                    if re.is_match(entry.path().file_name().unwrap().to_str().unwrap()) {
-                        if let Ok(raw_val) = crate::utils::read_line(entry.path().to_str().unwrap()) {
-                            if let Ok(val) = raw_val.parse::<u32>() {
-                                let key = entry.path().file_name().unwrap().to_str().unwrap().to_string();
-                                node_vals.insert(key, val);
+                .filter(|e|  e.file_type().is_file() || e.file_type().is_symlink()) {
+                    let file_name = entry.file_name().to_string_lossy().to_string();
+                    let caps = match re.captures(&file_name) {
+                        Some(caps) => caps,
+                        None => continue,
+                    };
+                    let class = match SensorClass::from_prefix(&caps["class"]) {
+                        Some(class) => class,
+                        None => continue,
+                    };
+                    let attr = caps.name("attr").map(|m| m.as_str());
+                    let index = caps["num"].parse::<u32>().unwrap_or_default();
+                    let input = match (class, attr) {
+                        (SensorClass::Pwm, None) => file_name.clone(),
+                        // power1_input wins over power1_average when both exist
+                        (SensorClass::Power, Some("_average")) => {
+                            if std::path::Path::new(&format!("{}/power{}_input", path, index)).exists() {
+                                continue;
                             }
-                        }
-                    }
+                            file_name.clone()
+                        },
+                        (SensorClass::Pwm, _) | (_, None) | (_, Some("_average")) => continue,
+                        _ => file_name.clone(),
+                    };
+                    sensors.push(Sensor::new(&path, class, index, &input));
         }
 
-        node_vals
+        sensors.sort_by_key(|s| (s.class, s.index));
+        sensors
     }
 
-    pub fn temps(&self) -> HashMap<String, u32> {
-        let mut node_vals: HashMap<String, u32> = HashMap::new();
-        for entry in walkdir::WalkDir::new(format!("{}/{}", ROOTPATH, self.node))
-                .sort_by_file_name()
-                .max_depth(1)
-                .into_iter()
-                .filter_map(Result::ok)
-                .filter(|e|  (e.file_type().is_file() || e.file_type().is_symlink())) {
-                    //println!("path: {:#?}", entry.path());
-                    let re = regex::Regex::new(r"^temp(?<num>\d{1,3})_input$").unwrap();
-                    if let Some(caps) = re.captures(entry.path().file_name().unwrap().to_str().unwrap()) {
-                        let num = caps["num"].to_string();
-
-                        
-                        let val = if let Ok(raw_val) = crate::utils::read_line(entry.path().to_str().unwrap()) {
-                            if let Ok(val) = raw_val.parse::<u32>() {
-                                val
-                            } else {
-                                continue;
-                            }
-                        } else {
-                            continue;
-                        };
-
-                        let key = if let Ok(val) = crate::utils::read_line(&format!("{}/{}/temp{}_label", ROOTPATH, self.node, num)) {
-                            val
-                        } else {
-                            format!("temp{}_label", num)
-                        };
-                        node_vals.insert(key, val);
-                    }
+    /// Fan speeds in RPM keyed by `fanN_input`.
+    pub fn fans(&self) -> HashMap<String, crate::error::Result<u32>> {
+        self.sensors()
+            .into_iter()
+            .filter(|s| s.class == SensorClass::Fan)
+            .map(|s| (s.input.clone(), s.value().map(|v| v as u32)))
+            .collect()
+    }
+
+    /// Temperatures in millidegree Celsius keyed by `tempN_label`.
+    pub fn temps(&self) -> HashMap<String, crate::error::Result<i32>> {
+        self.sensors()
+            .into_iter()
+            .filter(|s| s.class == SensorClass::Temp)
+            .map(|s| {
+                let key = match &s.label {
+                    Some(label) => label.clone(),
+                    None => format!("temp{}_label", s.index),
+                };
+                (key, s.value().map(|v| v as i32))
+            })
+            .collect()
+    }
+}
+
+// the device of a hwmon node below /sys/devices: the `device` link, else
+// the `<device>/hwmon/hwmonN` or `<device>/hwmonN` directory it lives in.
+// Chips registered without a device live in `virtual/hwmon` and are told
+// apart by `name`.
+fn device_path(path: &str, name: &str) -> Option<String> {
+    let devices = std::fs::canonicalize(crate::utils::rootpath("/sys/devices")).ok()?;
+    let device = match std::fs::canonicalize(std::path::Path::new(path).join("device")) {
+        Ok(p) => p,
+        Err(_) => {
+            let hwmon_dir = std::fs::canonicalize(path).ok()?;
+            let parent = hwmon_dir.parent()?;
+            if parent.file_name()? == "hwmon" {
+                parent.parent()?.to_path_buf()
+            } else {
+                parent.to_path_buf()
+            }
+        }
+    };
+    let relative = device.strip_prefix(&devices).ok()?.to_string_lossy().to_string();
+    if relative == "virtual" {
+        return Some(format!("virtual/{}", name));
+    }
+    Some(relative)
+}
+
+// `#N` suffixes for chips that share a device, in `name` then `hwmonN`
+// order, the first one keeps the plain id
+fn disambiguate(hwmons: &mut [Hwmon]) {
+    let number = |h: &Hwmon| h.node.trim_start_matches("hwmon").parse::<u32>().unwrap_or_default();
+    hwmons.sort_by(|a, b| (&a.id, &a.name, number(a)).cmp(&(&b.id, &b.name, number(b))));
+    let mut base: Option<String> = None;
+    let mut count = 0;
+    for hwmon in hwmons.iter_mut() {
+        if base.as_deref() == Some(hwmon.id.as_str()) {
+            count += 1;
+            hwmon.id = format!("{}#{}", hwmon.id, count);
+        } else {
+            base = Some(hwmon.id.clone());
+            count = 1;
+        }
+    }
+}
+
+/// hwmon sensor class, see the kernel's `Documentation/hwmon/sysfs-interface`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum SensorClass {
+    Voltage,
+    Current,
+    Power,
+    Energy,
+    Fan,
+    Temp,
+    Pwm,
+}
+
+impl SensorClass {
+    fn from_prefix(prefix: &str) -> Option<Self> {
+        match prefix {
+            "in" => Some(SensorClass::Voltage),
+            "curr" => Some(SensorClass::Current),
+            "power" => Some(SensorClass::Power),
+            "energy" => Some(SensorClass::Energy),
+            "fan" => Some(SensorClass::Fan),
+            "temp" => Some(SensorClass::Temp),
+            "pwm" => Some(SensorClass::Pwm),
+            _ => None,
+        }
+    }
+
+    /// sysfs attribute prefix, e.g. `in` for `in0_input`.
+    pub fn prefix(&self) -> &'static str {
+        match self {
+            SensorClass::Voltage => "in",
+            SensorClass::Current => "curr",
+            SensorClass::Power => "power",
+            SensorClass::Energy => "energy",
+            SensorClass::Fan => "fan",
+            SensorClass::Temp => "temp",
+            SensorClass::Pwm => "pwm",
+        }
+    }
+
+    /// Unit of the raw sysfs value.
+    pub fn unit(&self) -> &'static str {
+        match self {
+            SensorClass::Voltage => "mV",
+            SensorClass::Current => "mA",
+            SensorClass::Power => "µW",
+            SensorClass::Energy => "µJ",
+            SensorClass::Fan => "RPM",
+            SensorClass::Temp => "m°C",
+            SensorClass::Pwm => "/255",
+        }
+    }
+}
+
+/// One hwmon sensor with its label and limits.
+///
+/// Limits are read once when the sensor is enumerated, the reading on
+/// every [`Sensor::value`].
+#[derive(Clone, Debug)]
+pub struct Sensor {
+    pub class: SensorClass,
+    /// The `N` of `fanN_input`.
+    pub index: u32,
+    /// Attribute holding the reading, e.g. `in0_input`, `power1_average` or `pwm1`.
+    pub input: String,
+    pub label: Option<String>,
+    pub min: Option<i64>,
+    pub max: Option<i64>,
+    pub crit: Option<i64>,
+    pub alarm: Option<bool>,
+    path: String,
+}
+
+impl Sensor {
+    fn new(chip_path: &str, class: SensorClass, index: u32, input: &str) -> Self {
+        let attr = |suffix: &str| format!("{}/{}{}_{}", chip_path, class.prefix(), index, suffix);
+        Sensor {
+            class,
+            index,
+            input: input.to_string(),
+            label: crate::utils::read_value(&attr("label")).ok(),
+            min: crate::utils::read_value(&attr("min")).ok(),
+            max: crate::utils::read_value(&attr("max")).ok(),
+            crit: crate::utils::read_value(&attr("crit")).ok(),
+            alarm: crate::utils::read_value::<u8>(&attr("alarm")).ok().map(|v| v != 0),
+            path: format!("{}/{}", chip_path, input),
+        }
+    }
+
+    /// `label` if the driver provides one, else e.g. `in0`.
+    pub fn name(&self) -> String {
+        match &self.label {
+            Some(label) => label.clone(),
+            None => format!("{}{}", self.class.prefix(), self.index),
         }
+    }
+
+    /// Current reading in [`SensorClass::unit`].
+    pub fn value(&self) -> crate::error::Result<i64> {
+        crate::utils::read_value(&self.path)
+    }
+}
 
-        node_vals
+impl std::fmt::Display for Sensor {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}: ", self.name())?;
+        match self.value() {
+            Ok(v) => write!(f, "{} {}", v, self.class.unit())?,
+            Err(e) => write!(f, "ERROR: {}", e)?,
+        }
+        let limits: Vec<String> = [("min", self.min), ("max", self.max), ("crit", self.crit)]
+            .iter()
+            .filter_map(|(k, v)| v.map(|v| format!("{} {}", k, v)))
+            .collect();
+        if !limits.is_empty() {
+            write!(f, " ({})", limits.join(", "))?;
+        }
+        if self.alarm == Some(true) {
+            write!(f, " ALARM")?;
+        }
+        Ok(())
     }
 }
 
+/// Enumerate every hwmon chip.
 pub fn enumerate() -> Vec<Hwmon> {
     let mut hwmons = Vec::new();
-    for entry in walkdir::WalkDir::new(ROOTPATH)
+    let rootpath = crate::utils::rootpath(ROOTPATH);
+    let re = regex::Regex::new(r"^hwmon\d{1,3}$").unwrap();
+    for entry in walkdir::WalkDir::new(&rootpath)
             .sort_by_file_name()
             .max_depth(1) 
             .into_iter()
             .filter_map(Result::ok)
-            .filter(|e|  (e.file_type().is_dir() || e.file_type().is_symlink())){
-                if entry.path().to_str().unwrap() != ROOTPATH {
+            .filter(|e|  e.file_type().is_dir() || e.file_type().is_symlink()){
+                if entry.path().to_str().unwrap() != rootpath {
                     //println!("node: {:#?}", entry.path());
-                    let re = regex::Regex::new(r"^hwmon\d{1,3}$").unwrap();
-                    if re.is_match(entry.path().file_name().unwrap().to_str().unwrap().trim()) {
-                        if let Ok(hwmon) = Hwmon::new(entry.path().to_str().unwrap().trim()) {
+                    if re.is_match(&entry.file_name().to_string_lossy()) {
+                        if let Ok(hwmon) = Hwmon::new(&entry.path().to_string_lossy()) {
                             hwmons.push(hwmon)
                         }
                     }
                 }
     }
-    
+
+    // hwmonN order changes between boots, the id order doesn't
+    disambiguate(&mut hwmons);
     hwmons
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hwmon(node: &str, name: &str, id: &str) -> Hwmon {
+        Hwmon { node: node.to_string(), name: name.to_string(), id: id.to_string() }
+    }
+
+    #[test]
+    fn disambiguate_shared_devices() {
+        let mut hwmons = vec![
+            hwmon("hwmon7", "nct6798", "platform/nct6775.656"),
+            hwmon("hwmon2", "coretemp", "platform/coretemp.0"),
+            hwmon("hwmon5", "nct6798", "platform/nct6775.656"),
+            hwmon("hwmon4", "acpi_fan", "platform/nct6775.656"),
+        ];
+        disambiguate(&mut hwmons);
+        let ids: Vec<&str> = hwmons.iter().map(|h| h.id.as_str()).collect();
+        assert_eq!(ids, vec![
+            "platform/coretemp.0",
+            "platform/nct6775.656",
+            "platform/nct6775.656#2",
+            "platform/nct6775.656#3",
+        ]);
+        // by name, then by hwmonN
+        assert_eq!(hwmons[1].node, "hwmon4");
+        assert_eq!(hwmons[2].node, "hwmon5");
+        assert_eq!(hwmons[3].node, "hwmon7");
+    }
+}
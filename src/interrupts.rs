@@ -0,0 +1,118 @@
+// /proc/interrupts and /proc/softirqs
+use std::collections::HashMap;
+
+/// Per-cpu counters of every source in `/proc/interrupts` or
+/// `/proc/softirqs`.
+#[derive(Clone, Debug, Default)]
+pub struct Interrupts {
+    /// Online cpus, the `N` of each `CPUN` header column.
+    pub cpus: Vec<u32>,
+    /// Counts aligned with `cpus`, keyed by source.
+    ///
+    /// Numbered irqs are keyed `N/<device>`, e.g. `8/rtc0`, the others by
+    /// their name, e.g. `LOC` or `TIMER`.
+    pub sources: HashMap<String, Vec<u64>>,
+}
+
+fn read_interrupts(path: &str) -> crate::error::Result<Interrupts> {
+    let path = crate::utils::rootpath(path);
+    let data = crate::utils::read_all_line(&path).map_err(|e| crate::error::Error::from_io(&path, e))?;
+    parse_interrupts(path, data)
+}
+
+// `path` is only used for errors
+fn parse_interrupts(path: String, data: String) -> crate::error::Result<Interrupts> {
+    let mut lines = data.lines();
+    //            CPU0       CPU1
+    let cpus: Vec<u32> = match lines.next() {
+        Some(header) => header.split_whitespace()
+            .filter_map(|cpu| cpu.strip_prefix("CPU").and_then(|n| n.parse().ok()))
+            .collect(),
+        None => return Err(crate::error::Error::Parse { path, value: data }),
+    };
+
+    let mut interrupts = Interrupts { cpus, sources: HashMap::new() };
+    for line in lines {
+        //   8:          0          0   IO-APIC    8-edge      rtc0
+        // LOC:    1234567    1234567   Local timer interrupts
+        // ERR:          0
+        let (name, rest) = match line.split_once(':') {
+            Some((name, rest)) => (name.trim(), rest),
+            None => return Err(crate::error::Error::Parse { path, value: line.to_string() }),
+        };
+        let fields: Vec<&str> = rest.split_whitespace().collect();
+        let counts: Vec<u64> = fields.iter()
+            .take(interrupts.cpus.len())
+            .map_while(|v| v.parse::<u64>().ok())
+            .collect();
+        // ERR and MIS carry a single system wide count, kept on the
+        // first cpu
+        if counts.len() != interrupts.cpus.len() && counts.len() != 1 {
+            return Err(crate::error::Error::Parse { path, value: line.to_string() });
+        }
+        let key = match (name.parse::<u32>(), fields.last()) {
+            (Ok(_), Some(device)) if fields.len() > counts.len() => format!("{}/{}", name, device),
+            _ => name.to_string(),
+        };
+        let mut counts = counts;
+        counts.resize(interrupts.cpus.len(), 0);
+        interrupts.sources.insert(key, counts);
+    }
+    Ok(interrupts)
+}
+
+/// Read `/proc/interrupts`.
+pub fn interrupts() -> crate::error::Result<Interrupts> {
+    read_interrupts("/proc/interrupts")
+}
+
+/// Read `/proc/softirqs`.
+pub fn softirqs() -> crate::error::Result<Interrupts> {
+    read_interrupts("/proc/softirqs")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(data: &str) -> crate::error::Result<Interrupts> {
+        parse_interrupts("/proc/interrupts".to_string(), data.to_string())
+    }
+
+    #[test]
+    fn parse_sources() {
+        let interrupts = parse(concat!(
+            "           CPU0       CPU1       CPU3\n",
+            "   8:          0          1          2   IO-APIC    8-edge      rtc0\n",
+            " 145:     183012      90211          0   PCI-MSI 327680-edge      xhci_hcd\n",
+            " LOC:    7213041    6912876    6000000   Local timer interrupts\n",
+            " ERR:          3\n",
+            " MIS:          0\n",
+        )).unwrap();
+        assert_eq!(interrupts.cpus, vec![0, 1, 3]);
+        assert_eq!(interrupts.sources["8/rtc0"], vec![0, 1, 2]);
+        assert_eq!(interrupts.sources["145/xhci_hcd"], vec![183012, 90211, 0]);
+        assert_eq!(interrupts.sources["LOC"], vec![7213041, 6912876, 6000000]);
+        assert_eq!(interrupts.sources["ERR"], vec![3, 0, 0]);
+        assert_eq!(interrupts.sources.len(), 5);
+    }
+
+    #[test]
+    fn parse_softirqs() {
+        let softirqs = parse(concat!(
+            "                    CPU0       CPU1\n",
+            "          HI:          3          4\n",
+            "       TIMER:    1056718    1056756\n",
+        )).unwrap();
+        assert_eq!(softirqs.cpus, vec![0, 1]);
+        assert_eq!(softirqs.sources["HI"], vec![3, 4]);
+        assert_eq!(softirqs.sources["TIMER"], vec![1056718, 1056756]);
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(parse("").is_err());
+        assert!(parse("CPU0 CPU1\n  0: 12 13 IO-APIC\nbogus\n").is_err());
+        assert!(parse("CPU0 CPU1 CPU2\n  0: 12 13 IO-APIC 2-edge timer\n").is_err());
+    }
+}
@@ -0,0 +1,31 @@
+//! System monitor library.
+//!
+//! Enumerates cpus, batteries, thermal zones, hwmon chips and tunable
+//! sysfs/procfs nodes, and samples them into csv files through
+//! [`collector::Collector`]. The `system-monitor` binary is a thin CLI
+//! over this crate.
+//!
+//! Every path is resolved against [`utils::root`], so the crate can run
+//! against a captured fixture tree after [`utils::set_root`].
+
+pub mod error;
+pub mod utils;
+pub mod collector;
+pub mod visualization;
+
+pub mod config;
+pub mod cpu;
+pub mod battery;
+pub mod thermal;
+pub mod hwmon;
+pub mod memory;
+pub mod block;
+pub mod net;
+pub mod rapl;
+pub mod gpu;
+pub mod pressure;
+pub mod interrupts;
+pub mod rundown;
+pub mod estimate;
+pub mod stop;
+pub mod run;
@@ -1,18 +1,15 @@
-mod utils;
-mod collector;
-mod visualization;
-
-mod config;
-mod cpu;
-mod battery;
-mod thermal;
-mod hwmon;
+use system_monitor::{error, utils, visualization};
+use system_monitor::{config, battery, rundown, estimate, run, stop};
 
 use clap::{Parser, Subcommand};
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 struct Cli {
+    /// sysfs/procfs root directory, e.g. a captured fixture tree
+    #[arg(long="root", global=true, default_value="/")]
+    root: String,
+
     #[command(subcommand)]
     command: Option<Command>,
 }
@@ -29,9 +26,65 @@ enum Command {
         /// sum time to collect
         #[arg(short='t',long="time", default_value="120")]
         time: u32,
+        /// sampling interval in milliseconds
+        #[arg(short='i',long="interval", default_value="5000", value_parser=clap::value_parser!(u64).range(1..))]
+        interval: u64,
+        /// directory for the csv files, default ./run-<YYYYmmdd-HHMMSS>
+        #[arg(short='o',long="output-dir")]
+        output_dir: Option<String>,
+        /// stop once the combined battery capacity drops to this percent
+        #[arg(long="min-capacity", default_value="5", value_parser=clap::value_parser!(u32).range(0..=100))]
+        min_capacity: u32,
+        /// number of busiest interrupt sources written per sample
+        #[arg(long="top", default_value_t=run::DEFAULT_TOP)]
+        top: usize,
+        /// stop once a rule holds, e.g. "thermal.thermal_zone0 > 95000",
+        /// "battery.status == Full" or "power < 2W for 60s"; repeatable
+        #[arg(long="stop-when")]
+        stop_when: Vec<stop::StopRule>,
+    },
+    /// battery rundown test: collect on battery down to a capacity, then report
+    Rundown{
+        /// stop once the combined battery capacity drops to this percent
+        #[arg(long="target", default_value="5", value_parser=clap::value_parser!(u32).range(0..=100))]
+        target: u32,
+        /// config file to apply before the run, as written by `config show`
+        #[arg(short='f',long="file")]
+        file: Option<String>,
+        /// sampling interval in milliseconds
+        #[arg(short='i',long="interval", default_value="30000", value_parser=clap::value_parser!(u64).range(1..))]
+        interval: u64,
+        /// directory for the csv files, default ./run-<YYYYmmdd-HHMMSS>
+        #[arg(short='o',long="output-dir")]
+        output_dir: Option<String>,
+    },
+    /// battery health report
+    Battery{
+        /// print the health report as json, not with a subcommand
+        #[arg(long="json")]
+        json: bool,
+
+        #[command(subcommand)]
+        command: Option<BatteryCommand>,
     },
     /// visual data
-    Visual{},
+    Visual{
+        /// run directory written by collect, default the latest ./run-*
+        dir: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum BatteryCommand {
+    /// live time to empty/full estimate, until interrupted
+    Estimate{
+        /// sampling interval in milliseconds
+        #[arg(short='i',long="interval", default_value="5000", value_parser=clap::value_parser!(u64).range(1..))]
+        interval: u64,
+        /// seconds of samples the estimate is smoothed over
+        #[arg(short='w',long="window", default_value="300", value_parser=clap::value_parser!(u64).range(1..))]
+        window: u64,
+    },
 }
 
 #[derive(Subcommand)]
@@ -44,173 +97,63 @@ enum ConfigCommand {
     }
 }
 
-fn run_cmd_with_echo(cmd: &str, args: Vec<&str>) -> std::io::Result<()> {
-    println!(">>> {} {}", cmd, args.join(" "));
-    println!("{}", utils::run_cmd(cmd, args)?);
-    Ok(())
-}
-
-fn read_line_with_echo(path: &str) {
-    println!("+ {}", path);
-    match utils::read_line(path) {
-        Ok(v) => println!("{}", v),
-        Err(e) => println!("ERROR: {} {}", path, e),
-    };
-}
-
-fn read_all_wich_echo(path: &str) {
-    println!("+ {}", path);
-    match utils::read_all_line(path) {
-        Ok(v) => println!("{}", v),
-        Err(e) => println!("ERROR: {} {}", path, e),
-    };
-}
-
-fn show_os_info() -> std::io::Result<()>{
-    read_all_wich_echo("/etc/os-release");
-    run_cmd_with_echo("uname", vec!["-a"])?;
-
-    if let Ok(session_desktop) = std::env::var("XDG_SESSION_DESKTOP") {
-        println!("XDG_SESSION_DESKTOP={}", session_desktop);
-    }
+fn main() {
+    let cli = Cli::parse();
+    utils::set_root(&cli.root);
 
-    if let Ok(session_type) = std::env::var("XDG_SESSION_TYPE") {
-        println!("XDG_SESSION_TYPE={}", session_type);
+    if let Err(e) = dispatch(cli) {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
     }
-
-    Ok(())
-}
-
-fn show_dim_info() {
-    read_line_with_echo("/sys/class/dmi/id/product_version");
-    read_line_with_echo("/sys/class/dmi/id/product_name");
-    read_line_with_echo("/sys/class/dmi/id/sys_vendor");
-    read_line_with_echo("/sys/class/dmi/id/bios_version");
-    read_line_with_echo("/sys/class/dmi/id/ec_firmware_release");
 }
 
-fn main() -> std::io::Result<()> {
-    let cli = Cli::parse();
-
+fn dispatch(cli: Cli) -> error::Result<()> {
     if let Some(cmd) = cli.command {
         match cmd {
             Command::Config{command} => { 
                 match command {
                     ConfigCommand::Show {  } => {
-                        show_os_info()?;
-                        show_dim_info();
-                        println!("--------------");
-                        let configs = config::enumerate();
-                        for config in configs {
-                            println!("{}", config);
-                        }
-                        println!("--------------");
-
-                        let bats = battery::enumerate();
-                        if !bats.is_empty() {
-                            println!(">> battery <<");
-                            println!("{:#?}", bats);
-                        }
-                        
-                        let thermals = thermal::enumerate();
-                        if !thermals.is_empty() {
-                            println!(">> thermal <<");
-                            println!("{:#?}", thermals);
-                        }
-                        
-                        let hwmons = hwmon::enumerate();
-                        for hwmon in hwmons {
-                            let fans = hwmon.fans();
-                            let temps = hwmon.temps();
-                            if !fans.is_empty() || !temps.is_empty() {
-                                println!(">> hwmon name: {} <<", hwmon.name);
-                                if !fans.is_empty() {
-                                    println!("{:#?}", hwmon.fans());
-                                }
-                                if !temps.is_empty() {
-                                    println!("{:#?}", hwmon.temps());
-                                }
-                            }
-                        }
+                        config::show()?;
                     },
                     ConfigCommand::Apply { file } => {
-                        let configs = config::Config::load(&file);
-                        let local_configs = config::enumerate();
-
-                        let mut need_change = Vec::new();
-                        for config in &configs {
-                            for local_config in &local_configs {
-                                if config.node == local_config.node && local_config.value != None && config.value != local_config.value && local_config.writeable() {
-                                    let mut config_new = local_config.clone();
-                                    config_new.value = config.value.clone();
-                                    need_change.push(config_new);
-                                }
-                            }
-                        }
-
-                        for config in need_change {
-                            if let Some(val) = &config.value {
-                                println!("apply: {} = {}", config.node, val);
-                                if let Err(e) = config.apply() {
-                                    println!("ERROR: apply {} failed {}", config.node, e)
-                                }
-                            }
-                        }
+                        config::apply_file(&file)?;
                     }
                 }
             },
-            Command::Collect { time } => {
-                let mut collectors: Vec<Box<dyn collector::Collector>> = Vec::new();
-                let cpus = cpu::enumerate();
-                //println!("{}",cpus.len());
-                if cpus.len() > 0{
-                    let c = Box::new(collector::CpuCollector::new(cpus, "cpufreq.csv")?);
-                    collectors.push(c);
-                }
-                let bats = battery::enumerate();
-                if bats.len() > 0 {
-                    let c = Box::new(collector::CapacityCollector::new(bats.clone(), "capacity.csv", 5)?);
-                    collectors.push(c);
-
-                    let c = Box::new(collector::PowerCollector::new(bats, "power.csv")?);
-                    collectors.push(c);
-                }
-
-                let thermals = thermal::enumerate();
-                if thermals.len() > 0 {
-                    let c = Box::new(collector::ThermalCollector::new(thermals, "thermal.csv")?);
-                    collectors.push(c);
-                }
-                
-                let count = (time + 4) / 5;
-                'outer: for _ in 0..count {
-                    for c in &mut collectors {
-                        c.update()?;
-                        if c.need_stop() {
-                            break 'outer;
-                        }
+            Command::Battery { json: true, command: Some(_) } => {
+                return Err(error::Error::Invalid("--json only applies to the health report".to_string()));
+            },
+            Command::Battery { command: Some(BatteryCommand::Estimate { interval, window }), .. } => {
+                estimate::watch(interval, window)?;
+            },
+            Command::Battery { json, .. } => {
+                let healths: Vec<battery::Health> = battery::enumerate().iter().map(|b| b.health()).collect();
+                if json {
+                    let json = serde_json::to_string_pretty(&healths).map_err(|e| error::Error::Invalid(e.to_string()))?;
+                    println!("{}", json);
+                } else if healths.is_empty() {
+                    println!("no battery found");
+                } else {
+                    for health in healths {
+                        println!("{}", health);
                     }
-                    std::thread::sleep(std::time::Duration::from_secs(5));
-                }
-
-                // do once at end
-                for c in &mut collectors {
-                    c.update()?;
                 }
             },
-            Command::Visual {  } => {
-                if let Err(e) = visualization::show_datas("cpufreq.csv", "cpufreq.svg", "show cpu freq chart") {
-                    println!("WARNING: {}", e);
-                }
-                if let Err(e) = visualization::show_datas("capacity.csv", "capacity.svg", "show battery capacity chart") {
-                    println!("WARNING: {}", e);
-                }
-                if let Err(e) = visualization::show_datas("power.csv", "power.svg", "show battery power chart") {
-                    println!("WARNING: {}", e);
-                }
-                if let Err(e) = visualization::show_datas("thermal.csv", "thermal.svg", "show thermal chart") {
-                    println!("WARNING: {}", e);
-                }
+            Command::Collect { time, interval, output_dir, min_capacity, top, stop_when } => {
+                let dir = run::create_dir(output_dir.as_deref())?;
+                println!("output: {}", dir.display());
+                run::collect(&dir, Some((time as u64 * 1000).div_ceil(interval)), interval, min_capacity, top, stop_when, || None)?;
+            },
+            Command::Rundown { target, file, interval, output_dir } => {
+                let report = rundown::run(target, file.as_deref(), interval, output_dir.as_deref())?;
+                println!("{}", report);
+            },
+            Command::Visual { dir } => {
+                let dir = match dir {
+                    Some(d) => std::path::PathBuf::from(d),
+                    None => run::latest_dir(),
+                };
+                visualization::show_run(&dir);
             }
         }
     }
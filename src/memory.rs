@@ -0,0 +1,32 @@
+// /proc/meminfo and /proc/vmstat
+use std::collections::HashMap;
+
+// `Key: value [kB]` or `key value` lines
+fn read_counters(path: &str) -> crate::error::Result<HashMap<String, u64>> {
+    let path = crate::utils::rootpath(path);
+    let data = crate::utils::read_all_line(&path).map_err(|e| crate::error::Error::from_io(&path, e))?;
+
+    let mut counters = HashMap::new();
+    for line in data.lines() {
+        let mut fields = line.split_whitespace();
+        let (key, val) = match (fields.next(), fields.next()) {
+            (Some(key), Some(val)) => (key.trim_end_matches(':'), val),
+            _ => continue,
+        };
+        match val.parse::<u64>() {
+            Ok(v) => { counters.insert(key.to_string(), v); },
+            Err(_) => return Err(crate::error::Error::Parse { path, value: line.to_string() }),
+        }
+    }
+    Ok(counters)
+}
+
+/// `/proc/meminfo` in kB keyed by field name, e.g. `MemAvailable`.
+pub fn meminfo() -> crate::error::Result<HashMap<String, u64>> {
+    read_counters("/proc/meminfo")
+}
+
+/// `/proc/vmstat` event counters keyed by name, e.g. `pgfault`.
+pub fn vmstat() -> crate::error::Result<HashMap<String, u64>> {
+    read_counters("/proc/vmstat")
+}
@@ -0,0 +1,75 @@
+// /sys/class/net and /proc/net/dev
+use std::collections::HashMap;
+
+static ROOTPATH: &str = "/sys/class/net";
+
+/// Names of the network interfaces under `/sys/class/net`, `lo` and
+/// `docker*` excluded.
+pub fn enumerate() -> Vec<String> {
+    let mut ifaces = Vec::new();
+    let rootpath = crate::utils::rootpath(ROOTPATH);
+    for entry in walkdir::WalkDir::new(&rootpath)
+            .min_depth(1)
+            .max_depth(1)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_map(Result::ok)
+            .filter(|e|  e.file_type().is_dir() || e.file_type().is_symlink()) {
+                let name = entry.file_name().to_string_lossy().to_string();
+                if name != "lo" && !name.starts_with("docker") {
+                    ifaces.push(name);
+                }
+    }
+    ifaces
+}
+
+/// Cumulative counters of one `/proc/net/dev` line.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NetStat {
+    pub rx_bytes: u64,
+    pub rx_packets: u64,
+    pub rx_errs: u64,
+    pub rx_drop: u64,
+    pub tx_bytes: u64,
+    pub tx_packets: u64,
+    pub tx_errs: u64,
+    pub tx_drop: u64,
+}
+
+impl NetStat {
+    // fields after `iface:`
+    fn parse(fields: &[&str]) -> Option<Self> {
+        let val = |i: usize| fields.get(i).and_then(|v| v.parse::<u64>().ok());
+        Some(NetStat {
+            rx_bytes: val(0)?,
+            rx_packets: val(1)?,
+            rx_errs: val(2)?,
+            rx_drop: val(3)?,
+            tx_bytes: val(8)?,
+            tx_packets: val(9)?,
+            tx_errs: val(10)?,
+            tx_drop: val(11)?,
+        })
+    }
+}
+
+/// Read `/proc/net/dev` keyed by interface name.
+pub fn netdev() -> crate::error::Result<HashMap<String, NetStat>> {
+    let path = crate::utils::rootpath("/proc/net/dev");
+    let data = crate::utils::read_all_line(&path).map_err(|e| crate::error::Error::from_io(&path, e))?;
+
+    let mut stats = HashMap::new();
+    // two header lines, then `iface: counters`
+    for line in data.lines().skip(2) {
+        let (name, counters) = match line.split_once(':') {
+            Some(v) => v,
+            None => continue,
+        };
+        let fields: Vec<&str> = counters.split_whitespace().collect();
+        match NetStat::parse(&fields) {
+            Some(stat) => { stats.insert(name.trim().to_string(), stat); },
+            None => return Err(crate::error::Error::Parse { path, value: line.to_string() }),
+        }
+    }
+    Ok(stats)
+}
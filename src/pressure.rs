@@ -0,0 +1,94 @@
+// /proc/pressure and /proc/loadavg
+
+/// Resources with a `/proc/pressure` file.
+pub const RESOURCES: [&str; 3] = ["cpu", "memory", "io"];
+
+/// One `some` or `full` line of a `/proc/pressure` file.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Psi {
+    /// % of wall time stalled, averaged over 10s.
+    pub avg10: f64,
+    pub avg60: f64,
+    pub avg300: f64,
+    /// Cumulative stall time in µs.
+    pub total: u64,
+}
+
+impl Psi {
+    // avg10=0.00 avg60=0.00 avg300=0.00 total=0
+    fn parse(fields: &[&str]) -> Option<Self> {
+        let val = |key: &str| fields.iter().find_map(|f| f.strip_prefix(key)?.strip_prefix('='));
+        Some(Psi {
+            avg10: val("avg10")?.parse().ok()?,
+            avg60: val("avg60")?.parse().ok()?,
+            avg300: val("avg300")?.parse().ok()?,
+            total: val("total")?.parse().ok()?,
+        })
+    }
+}
+
+/// Stall information of one resource.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Pressure {
+    /// At least one task stalled.
+    pub some: Psi,
+    /// Every non-idle task stalled, missing for `cpu` before 5.13.
+    pub full: Option<Psi>,
+}
+
+/// Read `/proc/pressure/<resource>`, see [`RESOURCES`].
+pub fn pressure(resource: &str) -> crate::error::Result<Pressure> {
+    let path = crate::utils::rootpath(&format!("/proc/pressure/{}", resource));
+    let data = crate::utils::read_all_line(&path).map_err(|e| crate::error::Error::from_io(&path, e))?;
+
+    let mut some = None;
+    let mut full = None;
+    for line in data.lines() {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let psi = match fields.get(1..).and_then(Psi::parse) {
+            Some(psi) => psi,
+            None => return Err(crate::error::Error::Parse { path, value: line.to_string() }),
+        };
+        match fields[0] {
+            "some" => some = Some(psi),
+            "full" => full = Some(psi),
+            _ => {},
+        }
+    }
+    match some {
+        Some(some) => Ok(Pressure { some, full }),
+        None => Err(crate::error::Error::Parse { path, value: data }),
+    }
+}
+
+/// `/proc/loadavg`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LoadAvg {
+    pub load1: f64,
+    pub load5: f64,
+    pub load15: f64,
+    /// Runnable tasks at the time of the read.
+    pub running: u32,
+    /// Tasks in the system.
+    pub tasks: u32,
+}
+
+/// Read `/proc/loadavg`.
+pub fn loadavg() -> crate::error::Result<LoadAvg> {
+    let path = crate::utils::rootpath("/proc/loadavg");
+    let data = crate::utils::read_all_line(&path).map_err(|e| crate::error::Error::from_io(&path, e))?;
+
+    // 0.52 0.58 0.59 2/1043 12345
+    let fields: Vec<&str> = data.split_whitespace().collect();
+    let parse = || -> Option<LoadAvg> {
+        let (running, tasks) = fields.get(3)?.split_once('/')?;
+        Some(LoadAvg {
+            load1: fields.first()?.parse().ok()?,
+            load5: fields.get(1)?.parse().ok()?,
+            load15: fields.get(2)?.parse().ok()?,
+            running: running.parse().ok()?,
+            tasks: tasks.parse().ok()?,
+        })
+    };
+    parse().ok_or(crate::error::Error::Parse { path, value: data.trim().to_string() })
+}
@@ -0,0 +1,103 @@
+// /sys/class/powercap/intel-rapl:*
+static ROOTPATH: &str = "/sys/class/powercap";
+
+/// A RAPL power zone, e.g. `package-0` or its `core`, `uncore` and
+/// `dram` subzones.
+#[derive(Clone, Debug)]
+pub struct RaplZone {
+    /// Zone node name, e.g. `intel-rapl:0:1`.
+    pub node: String,
+    /// Zone `name`, prefixed with the parent zone for subzones, e.g.
+    /// `package-0/uncore`, and with `mmio/` for `intel-rapl-mmio` zones,
+    /// which repeat the names of the msr ones.
+    pub name: String,
+    /// `energy_uj` wraps to 0 after this value.
+    pub max_energy_range_uj: u64,
+}
+
+impl RaplZone {
+    /// Read the static attributes of the zone at `path`.
+    pub fn new(path: &str) -> crate::error::Result<Self> {
+        let node = match std::path::Path::new(path).file_name() {
+            Some(n) => n.to_string_lossy().to_string(),
+            None => return Err(crate::error::Error::NotFound(path.to_string())),
+        };
+        let name = crate::utils::read_value(&format!("{}/name", path))?;
+        let max_energy_range_uj = crate::utils::read_value(&format!("{}/max_energy_range_uj", path))?;
+
+        Ok(RaplZone{
+            node,
+            name,
+            max_energy_range_uj,
+        })
+    }
+
+    /// Energy counter in µJ, readable by root only on most kernels.
+    pub fn energy_uj(&self) -> crate::error::Result<u64> {
+        crate::utils::read_value(&format!("{}/{}/energy_uj", crate::utils::rootpath(ROOTPATH), self.node))
+    }
+
+    /// Energy used between two `energy_uj` readings, accounting for one
+    /// wraparound.
+    pub fn energy_delta(&self, prev: u64, cur: u64) -> u64 {
+        if cur >= prev {
+            cur - prev
+        } else {
+            (self.max_energy_range_uj - prev) + cur
+        }
+    }
+}
+
+/// Enumerate every RAPL zone, parents before their subzones.
+pub fn enumerate() -> Vec<RaplZone> {
+    let mut zones: Vec<RaplZone> = Vec::new();
+    let rootpath = crate::utils::rootpath(ROOTPATH);
+    let re = regex::Regex::new(r"^intel-rapl(-mmio)?(:\d{1,3})+$").unwrap();
+    for entry in walkdir::WalkDir::new(&rootpath)
+            .min_depth(1)
+            .max_depth(1)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_map(Result::ok)
+            .filter(|e|  (e.file_type().is_dir() || e.file_type().is_symlink()) && re.is_match(&e.file_name().to_string_lossy())) {
+                if let Ok(zone) = RaplZone::new(&entry.path().to_string_lossy()) {
+                    zones.push(zone);
+                }
+    }
+
+    // intel-rapl:0:1 is a subzone of intel-rapl:0
+    let parents = zones.clone();
+    for zone in &mut zones {
+        if let Some((parent_node, _)) = zone.node.rsplit_once(':') {
+            if let Some(parent) = parents.iter().find(|p| p.node == parent_node) {
+                zone.name = format!("{}/{}", parent.name, zone.name);
+            }
+        }
+    }
+    for zone in &mut zones {
+        if zone.node.starts_with("intel-rapl-mmio") {
+            zone.name = format!("mmio/{}", zone.name);
+        }
+    }
+    zones
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn zone() -> RaplZone {
+        RaplZone { node: "intel-rapl:0".to_string(), name: "package-0".to_string(), max_energy_range_uj: 262143328850 }
+    }
+
+    #[test]
+    fn energy_delta() {
+        assert_eq!(zone().energy_delta(1000, 1500), 500);
+        assert_eq!(zone().energy_delta(1000, 1000), 0);
+    }
+
+    #[test]
+    fn energy_delta_wraps() {
+        assert_eq!(zone().energy_delta(262143328000, 150), 850 + 150);
+    }
+}
@@ -0,0 +1,265 @@
+// a collect run: its output directory, collectors, signals and manifest
+use std::path::{Path, PathBuf};
+
+use crate::error::{Error, Result};
+use crate::{battery, block, collector, cpu, gpu, hwmon, interrupts, net, rapl, stop, thermal, utils};
+
+/// Prefix of the run directories created without `--output-dir`.
+pub const RUN_DIR_PREFIX: &str = "run-";
+/// Written to the run directory once collecting stops.
+pub const MANIFEST: &str = "manifest.json";
+/// One csv per chip and sensor class, `hwmon_<id>_<class>.csv`.
+pub const HWMON_CSV_PREFIX: &str = "hwmon_";
+/// Busiest interrupt sources written per sample.
+pub const DEFAULT_TOP: usize = 10;
+
+/// Create the directory for the csv files of a run, `output_dir` or else
+/// `./run-<YYYYmmdd-HHMMSS>`.
+pub fn create_dir(output_dir: Option<&str>) -> Result<PathBuf> {
+    if let Some(d) = output_dir {
+        // the csv files are truncated on create, never mix two runs
+        let dir = PathBuf::from(d);
+        std::fs::create_dir_all(&dir).map_err(|e| Error::from_io(d, e))?;
+        if std::fs::read_dir(&dir).map_err(|e| Error::from_io(d, e))?.next().is_some() {
+            return Err(Error::Invalid(format!("output directory {} is not empty", dir.display())));
+        }
+        return Ok(dir);
+    }
+
+    let since_epoch = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default();
+    let name = format!("{}{}", RUN_DIR_PREFIX, utils::utc_string(since_epoch.as_secs()));
+    // runs started within the same second get a -N suffix
+    let mut n = 0;
+    loop {
+        let dir = match n {
+            0 => PathBuf::from(&name),
+            _ => PathBuf::from(format!("{}-{}", name, n)),
+        };
+        match std::fs::create_dir(&dir) {
+            Ok(()) => return Ok(dir),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => n += 1,
+            Err(e) => return Err(Error::from_io(&dir.to_string_lossy(), e)),
+        }
+    }
+}
+
+/// The latest `./run-*` directory, `.` if there is none.
+pub fn latest_dir() -> PathBuf {
+    walkdir::WalkDir::new(".")
+        .min_depth(1)
+        .max_depth(1)
+        .into_iter()
+        .filter_map(std::result::Result::ok)
+        .filter(|e| e.file_type().is_dir())
+        .filter_map(|e| Some((run_order(&e.file_name().to_string_lossy())?, e.path().to_path_buf())))
+        .max()
+        .map(|(_, dir)| dir)
+        .unwrap_or_else(|| PathBuf::from("."))
+}
+
+// `run-20240101-120000-10` -> ("20240101-120000", 10): the start time,
+// then the -N suffix of runs started within the same second
+fn run_order(name: &str) -> Option<(String, u64)> {
+    let re = regex::Regex::new(r"^(?<time>\d{8}-\d{6})(?:-(?<n>\d+))?$").unwrap();
+    let caps = re.captures(name.strip_prefix(RUN_DIR_PREFIX)?)?;
+    let n = match caps.name("n") {
+        Some(n) => n.as_str().parse::<u64>().ok()?,
+        None => 0,
+    };
+    Some((caps["time"].to_string(), n))
+}
+
+#[derive(serde::Serialize)]
+struct Manifest {
+    args: Vec<String>,
+    root: String,
+    interval_ms: u64,
+    samples: u64,
+    /// `YYYYmmdd-HHMMSS` in UTC.
+    start: String,
+    stop: String,
+    start_ms: u64,
+    stop_ms: u64,
+    /// `completed`, the signal, or the stop rule that fired.
+    stop_reason: String,
+}
+
+// register SIGINT/SIGTERM: the first one is stored in the returned flag,
+// a second one exits right away
+fn register_signals() -> Result<std::sync::Arc<std::sync::atomic::AtomicUsize>> {
+    use signal_hook::consts::{SIGINT, SIGTERM};
+    let signal = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    for sig in [SIGINT, SIGTERM] {
+        let pending = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let register = || -> std::io::Result<()> {
+            signal_hook::flag::register_conditional_shutdown(sig, 1, pending.clone())?;
+            signal_hook::flag::register(sig, pending.clone())?;
+            signal_hook::flag::register_usize(sig, signal.clone(), sig as usize)?;
+            Ok(())
+        };
+        register().map_err(|e| Error::from_io(&signal_name(sig as usize), e))?;
+    }
+    Ok(signal)
+}
+
+fn signal_name(sig: usize) -> String {
+    match sig as i32 {
+        signal_hook::consts::SIGINT => "SIGINT".to_string(),
+        signal_hook::consts::SIGTERM => "SIGTERM".to_string(),
+        _ => format!("signal {}", sig),
+    }
+}
+
+// a collector for everything the system has, writing into `dir`
+fn collectors(dir: &Path, min_capacity: u32, top: usize) -> Result<Vec<Box<dyn collector::Collector>>> {
+    let path = |name: &str| dir.join(name).to_string_lossy().to_string();
+
+    let mut collectors: Vec<Box<dyn collector::Collector>> = Vec::new();
+    let cpus = cpu::enumerate();
+    if !cpus.is_empty(){
+        let c = Box::new(collector::CpuCollector::new(cpus, &path("cpufreq.csv"))?);
+        collectors.push(c);
+    }
+    // neither /proc/stat nor cpuidle need cpufreq
+    let all_cpus = cpu::enumerate_all();
+    let c = Box::new(collector::CpuLoadCollector::new(all_cpus.clone(), &path("cpuload.csv"))?);
+    collectors.push(c);
+
+    if all_cpus.iter().any(|cpu| cpu.idle_states().is_ok_and(|s| !s.is_empty())) {
+        let c = Box::new(collector::CStateCollector::new(all_cpus, &path("cstate.csv"))?);
+        collectors.push(c);
+    }
+
+    let c = Box::new(collector::MemoryCollector::new(&path("memory.csv"))?);
+    collectors.push(c);
+
+    let c = Box::new(collector::PressureCollector::new(&path("pressure.csv"))?);
+    collectors.push(c);
+
+    if let Ok(irqs) = interrupts::interrupts() {
+        let c = Box::new(collector::InterruptCollector::new(irqs.cpus, top, &path("interrupts.csv"))?);
+        collectors.push(c);
+    }
+
+    let disks = block::enumerate();
+    if !disks.is_empty() {
+        let c = Box::new(collector::DiskCollector::new(disks, &path("disk.csv"))?);
+        collectors.push(c);
+    }
+
+    let gpus = gpu::enumerate();
+    if gpus.iter().any(|g| g.kind != gpu::GpuKind::Other) {
+        let c = Box::new(collector::GpuCollector::new(gpus, &path("gpu.csv"))?);
+        collectors.push(c);
+    }
+
+    let ifaces = net::enumerate();
+    if !ifaces.is_empty() {
+        let c = Box::new(collector::NetCollector::new(ifaces, &path("net.csv"))?);
+        collectors.push(c);
+    }
+
+    let zones = rapl::enumerate();
+    if !zones.is_empty() {
+        let c = Box::new(collector::RaplCollector::new(zones, &path("rapl.csv"))?);
+        collectors.push(c);
+    }
+
+    let bats = battery::enumerate();
+    if !bats.is_empty() {
+        let c = Box::new(collector::CapacityCollector::new(bats.clone(), &path("capacity.csv"), min_capacity)?);
+        collectors.push(c);
+
+        let c = Box::new(collector::PowerCollector::new(bats.clone(), &path("power.csv"))?);
+        collectors.push(c);
+
+        let c = Box::new(collector::BatteryCollector::new(bats, battery::enumerate_mains(), &path("battery.csv"))?);
+        collectors.push(c);
+    }
+
+    let thermals = thermal::enumerate();
+    if !thermals.is_empty() {
+        let c = Box::new(collector::ThermalCollector::new(thermals, &path("thermal.csv"))?);
+        collectors.push(c);
+    }
+
+    for hwmon in hwmon::enumerate() {
+        let mut classes: Vec<hwmon::SensorClass> = hwmon.sensors().iter().map(|s| s.class).collect();
+        classes.dedup();
+        for class in classes {
+            let chip = hwmon.id.replace(['/', ':'], "_");
+            let file = path(&format!("{}{}_{}.csv", HWMON_CSV_PREFIX, chip, class.prefix()));
+            let c = Box::new(collector::HwmonCollector::new(&hwmon, class, &file)?);
+            collectors.push(c);
+        }
+    }
+    Ok(collectors)
+}
+
+/// Sample every collector into `dir` every `interval` ms, `count` times or
+/// until one needs to stop, a stop rule holds, SIGINT/SIGTERM arrives or
+/// `stop` gives a reason, then write the manifest. `stop` is polled like
+/// the signals, while waiting for each tick. Returns why it stopped early.
+pub fn collect(dir: &Path, count: Option<u64>, interval: u64, min_capacity: u32, top: usize, mut rules: Vec<stop::StopRule>, stop: impl Fn() -> Option<String>) -> Result<Option<String>> {
+    let mut collectors = collectors(dir, min_capacity, top)?;
+    {
+        let outputs: Vec<&collector::Output> = collectors.iter().map(|c| c.output()).collect();
+        if let Some(rule) = rules.iter().find(|rule| !rule.matches(&outputs)) {
+            return Err(Error::Invalid(format!("stop rule {:?} matches no csv column", rule.to_string())));
+        }
+    }
+
+    let signal = register_signals()?;
+    let interrupt = || match signal.load(std::sync::atomic::Ordering::Relaxed) {
+        0 => stop(),
+        sig => Some(signal_name(sig)),
+    };
+    let mut clock = collector::Clock::new(std::time::Duration::from_millis(interval));
+    let summary = collector::sample(&mut collectors, &mut clock, count, &mut rules, interrupt)?;
+    if let Some(reason) = &summary.reason {
+        println!("stop: {}", reason);
+    }
+
+    let manifest = Manifest {
+        args: std::env::args().collect(),
+        root: utils::root().to_string_lossy().to_string(),
+        interval_ms: interval,
+        samples: summary.samples,
+        start: utils::utc_string(summary.start.millis / 1000),
+        stop: utils::utc_string(summary.stop.millis / 1000),
+        start_ms: summary.start.millis,
+        stop_ms: summary.stop.millis,
+        stop_reason: summary.reason.clone().unwrap_or_else(|| "completed".to_string()),
+    };
+    let file = dir.join(MANIFEST);
+    let json = serde_json::to_string_pretty(&manifest).map_err(|e| Error::Invalid(e.to_string()))?;
+    std::fs::write(&file, json + "\n").map_err(|e| Error::from_io(&file.to_string_lossy(), e))?;
+    Ok(summary.reason)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn run_order_by_time_then_suffix() {
+        let mut names = vec![
+            "run-20240101-120000-10",
+            "run-20240101-120000",
+            "run-20231231-235959-3",
+            "run-20240101-120000-9",
+            "run-20240101-120000-1",
+        ];
+        names.sort_by_key(|n| run_order(n));
+        assert_eq!(names, vec![
+            "run-20231231-235959-3",
+            "run-20240101-120000",
+            "run-20240101-120000-1",
+            "run-20240101-120000-9",
+            "run-20240101-120000-10",
+        ]);
+        assert_eq!(run_order("run-latest"), None);
+        assert_eq!(run_order("output"), None);
+    }
+}
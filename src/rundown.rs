@@ -0,0 +1,258 @@
+// battery rundown run and its report from the capacity.csv and power.csv
+// of the run directory
+use std::path::Path;
+
+/// Report file written into the run directory.
+pub const REPORT: &str = "rundown.txt";
+/// Stop reason of a rundown cut short by the batteries no longer
+/// discharging.
+pub const OFF_BATTERY: &str = "no longer on battery";
+
+/// Summary of a battery rundown, see [`report`].
+#[derive(Clone, Debug, Default)]
+pub struct Report {
+    /// Length of the run in seconds.
+    pub duration: f64,
+    /// Combined capacity in % at the first and last sample.
+    pub start_capacity: Option<f64>,
+    pub end_capacity: Option<f64>,
+    /// Energy drawn from the batteries, integrated from `power.csv`.
+    pub consumed_wh: Option<f64>,
+    pub avg_power_w: Option<f64>,
+    /// Energy of the batteries when full.
+    pub full_wh: Option<f64>,
+    /// Runtime from full to empty at `avg_power_w`, in hours.
+    pub projected_runtime_h: Option<f64>,
+    /// Each 10% band of combined capacity the run went through, from the
+    /// highest band down.
+    pub bands: Vec<Band>,
+}
+
+/// Time spent and energy drawn while the combined capacity was within
+/// one 10% band.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Band {
+    /// Lower bound in %, e.g. 80 for 80-90%.
+    pub lower: u32,
+    pub secs: f64,
+    pub wh: f64,
+}
+
+// 10% band of a capacity, 100% counts toward 90-100%
+fn band(capacity: f64) -> u32 {
+    ((capacity.clamp(0.0, 99.9) / 10.0) as u32) * 10
+}
+
+// Wh drawn between each two samples of `power` in W, trapezoidal, with the
+// start of the interval
+fn energy(power: &[(f64, f64)]) -> impl Iterator<Item = (f64, f64)> + '_ {
+    power.windows(2).map(|pair| {
+        let ((t0, w0), (t1, w1)) = (pair[0], pair[1]);
+        (t0, (w0 + w1) / 2.0 * (t1 - t0) / 3600.0)
+    })
+}
+
+// each interval counts toward the band the capacity was in at its start
+fn bands(capacity: &[(f64, f64)], power: &[(f64, f64)]) -> Vec<Band> {
+    let mut bands: Vec<Band> = Vec::new();
+    let mut add = |lower: u32, secs: f64, wh: f64| {
+        match bands.iter_mut().find(|b| b.lower == lower) {
+            Some(b) => {
+                b.secs += secs;
+                b.wh += wh;
+            },
+            None => bands.push(Band { lower, secs, wh }),
+        }
+    };
+    for pair in capacity.windows(2) {
+        let ((t0, c0), (t1, _)) = (pair[0], pair[1]);
+        add(band(c0), t1 - t0, 0.0);
+    }
+    for (t0, wh) in energy(power) {
+        // the capacity sample taken at or before t0
+        let i = capacity.partition_point(|(t, _)| *t <= t0).saturating_sub(1);
+        if let Some((_, c)) = capacity.get(i) {
+            add(band(*c), 0.0, wh);
+        }
+    }
+    bands.sort_by_key(|b| std::cmp::Reverse(b.lower));
+    bands
+}
+
+// (elapsed seconds, value) of each row, `value` from the cells after the
+// time columns
+fn read_series(file: &Path, value: impl Fn(&csv::StringRecord, &csv::StringRecord) -> Option<f64>) -> crate::error::Result<Vec<(f64, f64)>> {
+    let path = file.to_string_lossy();
+    let mut rdr = csv::Reader::from_path(file).map_err(|e| crate::error::Error::from_csv(&path, e))?;
+    let headers = rdr.headers().map_err(|e| crate::error::Error::from_csv(&path, e))?.clone();
+    let elapsed = crate::collector::Timestamp::header().len() - 1;
+    let mut series = Vec::new();
+    for result in rdr.records() {
+        let record = result.map_err(|e| crate::error::Error::from_csv(&path, e))?;
+        let secs = match record.get(elapsed).and_then(|v| v.parse::<f64>().ok()) {
+            Some(ms) => ms / 1000.0,
+            None => continue,
+        };
+        if let Some(v) = value(&headers, &record) {
+            series.push((secs, v));
+        }
+    }
+    Ok(series)
+}
+
+/// Build the report of the run in `dir`, `full_wh` is the energy of the
+/// batteries when full, see [`crate::battery::Battery::energy`].
+pub fn report(dir: &Path, full_wh: Option<f64>) -> crate::error::Result<Report> {
+    let time_columns = crate::collector::Timestamp::header().len();
+    let capacity = read_series(&dir.join("capacity.csv"), |headers, record| {
+        let index = headers.iter().position(|h| h == "combined")?;
+        record.get(index)?.parse::<f64>().ok()
+    })?;
+    // µW of every battery, summed
+    let power = read_series(&dir.join("power.csv"), |_, record| {
+        let cells: Vec<f64> = record.iter().skip(time_columns).filter_map(|v| v.parse::<f64>().ok()).collect();
+        if cells.is_empty() {
+            None
+        } else {
+            Some(cells.iter().sum::<f64>() / 1e6)
+        }
+    })?;
+
+    let mut report = Report {
+        full_wh,
+        start_capacity: capacity.first().map(|(_, c)| *c),
+        end_capacity: capacity.last().map(|(_, c)| *c),
+        ..Default::default()
+    };
+    if let (Some((first, _)), Some((last, _))) = (capacity.first(), capacity.last()) {
+        report.duration = last - first;
+    }
+
+    let wh: f64 = energy(&power).map(|(_, wh)| wh).sum();
+    let power_secs = match (power.first(), power.last()) {
+        (Some((first, _)), Some((last, _))) => last - first,
+        _ => 0.0,
+    };
+    if power_secs > 0.0 {
+        report.consumed_wh = Some(wh);
+        report.avg_power_w = Some(wh * 3600.0 / power_secs);
+    }
+    report.projected_runtime_h = match (full_wh, report.avg_power_w) {
+        (Some(full), Some(avg)) if avg > 0.0 => Some(full / avg),
+        _ => None,
+    };
+
+    report.bands = bands(&capacity, &power);
+    Ok(report)
+}
+
+/// Collect on battery until the combined capacity drops to `target`
+/// percent, or until the batteries stop discharging, e.g. the AC adapter
+/// was plugged in, after applying the config `file` if given. Then write
+/// the report into the run directory and return it.
+pub fn run(target: u32, file: Option<&str>, interval: u64, output_dir: Option<&str>) -> crate::error::Result<Report> {
+    if !crate::battery::on_battery() {
+        return Err(crate::error::Error::Invalid("not on battery, unplug the AC adapter first".to_string()));
+    }
+    if let Some(file) = file {
+        crate::config::apply_file(file)?;
+    }
+    let dir = crate::run::create_dir(output_dir)?;
+    println!("output: {}", dir.display());
+    let unplugged = || match crate::battery::on_battery() {
+        true => None,
+        false => Some(OFF_BATTERY.to_string()),
+    };
+    crate::run::collect(&dir, None, interval, target, crate::run::DEFAULT_TOP, Vec::new(), unplugged)?;
+
+    let full_wh = crate::battery::enumerate().iter()
+        .map(|b| b.energy().map(|(_, full)| full))
+        .sum::<crate::error::Result<f64>>()
+        .ok();
+    let report = report(&dir, full_wh)?;
+    let file = dir.join(REPORT);
+    std::fs::write(&file, format!("{}\n", report)).map_err(|e| crate::error::Error::from_io(&file.to_string_lossy(), e))?;
+    Ok(report)
+}
+
+impl std::fmt::Display for Report {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let or_unknown = |v: Option<String>| v.unwrap_or_else(|| "unknown".to_string());
+        writeln!(f, "duration:           {}", crate::utils::hms_string(self.duration))?;
+        writeln!(f, "capacity:           {} -> {}",
+            or_unknown(self.start_capacity.map(|c| format!("{:.1}%", c))),
+            or_unknown(self.end_capacity.map(|c| format!("{:.1}%", c))))?;
+        writeln!(f, "consumed:           {}", or_unknown(self.consumed_wh.map(|wh| format!("{:.2} Wh", wh))))?;
+        writeln!(f, "average power:      {}", or_unknown(self.avg_power_w.map(|w| format!("{:.2} W", w))))?;
+        writeln!(f, "full capacity:      {}", or_unknown(self.full_wh.map(|wh| format!("{:.2} Wh", wh))))?;
+        write!(f, "projected runtime:  {}", or_unknown(self.projected_runtime_h.map(|h| crate::utils::hms_string(h * 3600.0))))?;
+        for band in &self.bands {
+            write!(f, "\n  {:>3}-{}%: {} {:>7.2} Wh", band.lower, band.lower + 10, crate::utils::hms_string(band.secs), band.wh)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 100% to 79.5% in 30 minutes, drawing 6 W, then 12 W, then 6 W again
+    const CAPACITY: [(f64, f64); 4] = [(0.0, 100.0), (600.0, 90.0), (1200.0, 89.9), (1800.0, 79.5)];
+    const POWER: [(f64, f64); 4] = [(0.0, 6.0), (600.0, 12.0), (1200.0, 12.0), (1800.0, 6.0)];
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn energy_is_trapezoidal() {
+        let wh: Vec<(f64, f64)> = energy(&POWER).collect();
+        assert_eq!(wh.len(), 3);
+        assert!(close(wh[0].1, 1.5));
+        assert!(close(wh[1].1, 2.0));
+        assert!(close(wh[2].1, 1.5));
+        assert_eq!(energy(&POWER[..1]).count(), 0);
+    }
+
+    #[test]
+    fn bands_split_at_ten_percent() {
+        assert_eq!(band(100.0), 90);
+        assert_eq!(band(90.0), 90);
+        assert_eq!(band(89.9), 80);
+        assert_eq!(band(0.0), 0);
+
+        let bands = bands(&CAPACITY, &POWER);
+        assert_eq!(bands.len(), 2, "the last sample starts no interval");
+        assert_eq!((bands[0].lower, bands[0].secs), (90, 1200.0));
+        assert!(close(bands[0].wh, 3.5));
+        assert_eq!((bands[1].lower, bands[1].secs), (80, 600.0));
+        assert!(close(bands[1].wh, 1.5));
+    }
+
+    #[test]
+    fn report_from_run_dir() {
+        let dir = std::env::temp_dir().join(format!("system-monitor-rundown-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut capacity = "timestamp_ms,elapsed_ms,BAT0,combined,stop\n".to_string();
+        let mut power = "timestamp_ms,elapsed_ms,BAT0,stop\n".to_string();
+        for ((t, c), (_, w)) in CAPACITY.iter().zip(POWER.iter()) {
+            let ms = (t * 1000.0) as u64;
+            capacity += &format!("{},{},{},{:.1},\n", 1_000_000 + ms, ms, *c as u32, c);
+            power += &format!("{},{},{},\n", 1_000_000 + ms, ms, (w * 1e6) as u64);
+        }
+        std::fs::write(dir.join("capacity.csv"), capacity).unwrap();
+        std::fs::write(dir.join("power.csv"), power).unwrap();
+
+        let report = report(&dir, Some(50.0)).unwrap();
+        assert_eq!(report.duration, 1800.0);
+        assert_eq!(report.start_capacity, Some(100.0));
+        assert_eq!(report.end_capacity, Some(79.5));
+        assert!(close(report.consumed_wh.unwrap(), 5.0));
+        assert!(close(report.avg_power_w.unwrap(), 10.0));
+        assert!(close(report.projected_runtime_h.unwrap(), 5.0));
+        assert_eq!(report.bands.iter().map(|b| b.lower).collect::<Vec<_>>(), vec![90, 80]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,263 @@
+// --stop-when rules over the latest sample of every collector
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Op {
+    Gt,
+    Ge,
+    Lt,
+    Le,
+    Eq,
+    Ne,
+}
+
+#[derive(Clone, Debug)]
+enum Threshold {
+    Number(f64),
+    Text(String),
+}
+
+/// A rule that stops collecting, e.g. `thermal.thermal_zone0 > 95000`,
+/// `battery.status == Full` or `power < 2W for 60s`.
+///
+/// The left side is a collector csv name and optionally a column. A
+/// column matches by its full name or by the part after a `:`, so
+/// `battery.status` checks `BAT0:status` and `BAT1:status`, and `power`
+/// alone checks every column of `power.csv`. The rule holds when any
+/// matching column does.
+///
+/// Numbers may carry a unit, converted to the unit sysfs reports:
+/// `W`, `V`, `A`, `Wh` and `J` to µ-units, `C` to m°C.
+#[derive(Clone, Debug)]
+pub struct StopRule {
+    text: String,
+    source: String,
+    op: Op,
+    threshold: Threshold,
+    /// How long the rule must hold, in ms.
+    hold: u64,
+    // elapsed ms of the first sample of the current run of holds
+    since: Option<u64>,
+}
+
+// `2W` -> 2000000, `95C` -> 95000, `50%` -> 50
+fn parse_number(value: &str) -> Option<Result<f64, String>> {
+    let re = regex::Regex::new(r"^(?<num>-?\d+(?:\.\d+)?)\s*(?<unit>[a-zA-Zµ°%]*)$").unwrap();
+    let caps = re.captures(value)?;
+    let num = caps["num"].parse::<f64>().ok()?;
+    let scale = match &caps["unit"] {
+        "" | "%" => 1.0,
+        "W" | "V" | "A" | "Wh" | "J" => 1e6,
+        "mW" | "mV" | "mA" | "mWh" | "mJ" => 1e3,
+        "uW" | "uV" | "uA" | "uWh" | "uJ" | "µW" | "µV" | "µA" | "µWh" | "µJ" => 1.0,
+        "C" | "°C" => 1e3,
+        unit => return Some(Err(format!("unknown unit {:?}", unit))),
+    };
+    Some(Ok(num * scale))
+}
+
+// `500ms`, `60s`, `5m`, `1h` in ms
+fn parse_duration(value: &str) -> Option<u64> {
+    let re = regex::Regex::new(r"^(?<num>\d+)(?<unit>ms|s|m|h)$").unwrap();
+    let caps = re.captures(value)?;
+    let num = caps["num"].parse::<u64>().ok()?;
+    let scale = match &caps["unit"] {
+        "ms" => 1,
+        "s" => 1000,
+        "m" => 60 * 1000,
+        _ => 60 * 60 * 1000,
+    };
+    Some(num * scale)
+}
+
+impl FromStr for StopRule {
+    type Err = crate::error::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = |why: &str| crate::error::Error::Invalid(format!("stop rule {:?}: {}", s, why));
+        let re = regex::Regex::new(r"^\s*(?<source>.+?)\s*(?<op>>=|<=|==|!=|>|<)\s*(?<value>.+?)(?:\s+for\s+(?<hold>\S+))?\s*$").unwrap();
+        let caps = re.captures(s).ok_or_else(|| invalid("expected `<csv>[.<column>] <op> <value> [for <duration>]`"))?;
+        let op = match &caps["op"] {
+            ">" => Op::Gt,
+            ">=" => Op::Ge,
+            "<" => Op::Lt,
+            "<=" => Op::Le,
+            "==" => Op::Eq,
+            _ => Op::Ne,
+        };
+        let threshold = match parse_number(&caps["value"]) {
+            Some(Ok(num)) => Threshold::Number(num),
+            Some(Err(why)) => return Err(invalid(&why)),
+            None if op == Op::Eq || op == Op::Ne => Threshold::Text(caps["value"].to_string()),
+            None => return Err(invalid("only == and != compare text")),
+        };
+        let hold = match caps.name("hold") {
+            Some(hold) => parse_duration(hold.as_str()).ok_or_else(|| invalid("expected a duration like 500ms, 60s, 5m or 1h"))?,
+            None => 0,
+        };
+
+        Ok(StopRule {
+            text: s.trim().to_string(),
+            source: caps["source"].to_string(),
+            op,
+            threshold,
+            hold,
+            since: None,
+        })
+    }
+}
+
+impl StopRule {
+    // the output the source names and the column part, if any; csv names
+    // may contain dots, so the longest matching name wins
+    fn resolve<'a>(&self, outputs: &[&'a crate::collector::Output]) -> Option<(&'a crate::collector::Output, Option<String>)> {
+        outputs.iter()
+            .filter_map(|output| {
+                if self.source == output.name() {
+                    Some((*output, None))
+                } else {
+                    let column = self.source.strip_prefix(output.name())?.strip_prefix('.')?;
+                    Some((*output, Some(column.to_string())))
+                }
+            })
+            .max_by_key(|(output, _)| output.name().len())
+    }
+
+    fn columns<'a>(output: &'a crate::collector::Output, column: &Option<String>) -> Vec<&'a String> {
+        output.header()
+            .iter()
+            .skip(crate::collector::Timestamp::header().len())
+            .filter(|h| match column {
+                Some(c) => *h == c || h.rsplit_once(':').is_some_and(|(_, name)| name == c),
+                None => true,
+            })
+            .collect()
+    }
+
+    /// Whether the rule names at least one column of `outputs`.
+    pub fn matches(&self, outputs: &[&crate::collector::Output]) -> bool {
+        match self.resolve(outputs) {
+            Some((output, column)) => !Self::columns(output, &column).is_empty(),
+            None => false,
+        }
+    }
+
+    fn holds(&self, value: &str) -> bool {
+        match &self.threshold {
+            Threshold::Number(threshold) => {
+                let value = match value.parse::<f64>() {
+                    Ok(v) => v,
+                    Err(_) => return false,
+                };
+                match self.op {
+                    Op::Gt => value > *threshold,
+                    Op::Ge => value >= *threshold,
+                    Op::Lt => value < *threshold,
+                    Op::Le => value <= *threshold,
+                    Op::Eq => value == *threshold,
+                    Op::Ne => value != *threshold,
+                }
+            },
+            Threshold::Text(text) => match self.op {
+                Op::Eq => value == text,
+                _ => value != text,
+            },
+        }
+    }
+
+    /// Check the latest sample of `outputs` taken at `elapsed` ms, true
+    /// once the rule has held for its duration.
+    pub fn check(&mut self, outputs: &[&crate::collector::Output], elapsed: u64) -> bool {
+        let holds = match self.resolve(outputs) {
+            Some((output, column)) => Self::columns(output, &column)
+                .iter()
+                .filter_map(|c| output.value(c))
+                .any(|v| self.holds(v)),
+            None => false,
+        };
+        if !holds {
+            self.since = None;
+            return false;
+        }
+        let since = *self.since.get_or_insert(elapsed);
+        elapsed - since >= self.hold
+    }
+}
+
+impl std::fmt::Display for StopRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn number(rule: &StopRule) -> f64 {
+        match rule.threshold {
+            Threshold::Number(n) => n,
+            Threshold::Text(ref t) => panic!("text threshold {:?}", t),
+        }
+    }
+
+    #[test]
+    fn parse_column_rule() {
+        let rule: StopRule = "thermal.thermal_zone0 > 95000".parse().unwrap();
+        assert_eq!(rule.source, "thermal.thermal_zone0");
+        assert_eq!(rule.op, Op::Gt);
+        assert_eq!(number(&rule), 95000.0);
+        assert_eq!(rule.hold, 0);
+        assert_eq!(rule.to_string(), "thermal.thermal_zone0 > 95000");
+    }
+
+    #[test]
+    fn parse_units_and_duration() {
+        let rule: StopRule = " power < 2W for 60s ".parse().unwrap();
+        assert_eq!(rule.source, "power");
+        assert_eq!(rule.op, Op::Lt);
+        assert_eq!(number(&rule), 2e6);
+        assert_eq!(rule.hold, 60 * 1000);
+        assert_eq!(rule.to_string(), "power < 2W for 60s");
+
+        let rule: StopRule = "battery.voltage_uV<=11500mV for 5m".parse().unwrap();
+        assert_eq!(rule.op, Op::Le);
+        assert_eq!(number(&rule), 11.5e6);
+        assert_eq!(rule.hold, 5 * 60 * 1000);
+
+        assert_eq!(number(&"thermal >= 95C".parse().unwrap()), 95000.0);
+        assert_eq!(number(&"capacity.combined <= 5%".parse().unwrap()), 5.0);
+        assert_eq!("rapl.package-0 > 0.5 for 1h".parse::<StopRule>().unwrap().hold, 60 * 60 * 1000);
+    }
+
+    #[test]
+    fn parse_text_rule() {
+        let rule: StopRule = "battery.status == Full".parse().unwrap();
+        assert_eq!(rule.op, Op::Eq);
+        assert!(matches!(rule.threshold, Threshold::Text(ref t) if t == "Full"));
+        assert!(rule.holds("Full"));
+        assert!(!rule.holds("Charging"));
+
+        let rule: StopRule = "battery.status != Discharging".parse().unwrap();
+        assert!(rule.holds("Full"));
+        assert!(!rule.holds("Discharging"));
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!("thermal".parse::<StopRule>().is_err());
+        assert!("battery.status > Full".parse::<StopRule>().is_err());
+        assert!("power < 2kW".parse::<StopRule>().is_err());
+        assert!("power < 2W for 60".parse::<StopRule>().is_err());
+        assert!("power < 2W for 1d".parse::<StopRule>().is_err());
+    }
+
+    #[test]
+    fn holds_compares_numbers() {
+        let rule: StopRule = "thermal > 95C".parse().unwrap();
+        assert!(rule.holds("95001"));
+        assert!(!rule.holds("95000"));
+        assert!(!rule.holds(""));
+        assert!(!rule.holds("n/a"));
+    }
+}
@@ -1,7 +1,9 @@
 static ROOTPATH: &str = "/sys/class/thermal";
 
+/// A `thermal_zoneN` under `/sys/class/thermal`.
 #[derive(Clone, Debug)]
 pub struct Thermal {
+    /// Zone node name, e.g. `thermal_zone0`.
     pub name: String,
     rtype: String,
     mode: String,
@@ -10,17 +12,16 @@ pub struct Thermal {
 }
 
 impl Thermal {
-    pub fn new(path: &str) -> std::io::Result<Self> {
-        let path = std::path::Path::new(path);
-        let name = path.file_name().unwrap().to_str().unwrap().to_string();
-        let rtype = crate::utils::read_line(&path.join("type").to_str().unwrap().to_string())?;
-        let mode = if let Ok(v) = crate::utils::read_line(&path.join("mode").to_str().unwrap().to_string()) {
-            v
-        } else {
-            "".to_string()
+    /// Read the static attributes of the thermal zone at `path`.
+    pub fn new(path: &str) -> crate::error::Result<Self> {
+        let name = match std::path::Path::new(path).file_name() {
+            Some(n) => n.to_string_lossy().to_string(),
+            None => return Err(crate::error::Error::NotFound(path.to_string())),
         };
-        let available_policies = crate::utils::read_line(&path.join("available_policies").to_str().unwrap().to_string())?;
-        let policy = crate::utils::read_line(&path.join("policy").to_str().unwrap().to_string())?;
+        let rtype = crate::utils::read_value(&format!("{}/type", path))?;
+        let mode = crate::utils::read_value(&format!("{}/mode", path)).unwrap_or_default();
+        let available_policies = crate::utils::read_value(&format!("{}/available_policies", path))?;
+        let policy = crate::utils::read_value(&format!("{}/policy", path))?;
 
         Ok(Thermal{
             name,
@@ -30,26 +31,41 @@ impl Thermal {
             policy,
         })
     }
-    pub fn temp(&self) -> u32 {
-        if let Ok(raw_val) = crate::utils::read_line(&format!("{}/{}/temp", ROOTPATH, self.name)) {
-            if let Ok(val) = raw_val.parse::<u32>() {
-                return val
-            } else {
-                return 0;
-            }
-        }
-        return 0;
+
+    /// Zone `type`, e.g. `x86_pkg_temp` or `acpitz`.
+    pub fn rtype(&self) -> &str {
+        &self.rtype
+    }
+
+    pub fn mode(&self) -> &str {
+        &self.mode
+    }
+
+    pub fn available_policies(&self) -> &str {
+        &self.available_policies
+    }
+
+    pub fn policy(&self) -> &str {
+        &self.policy
+    }
+
+    /// Temperature in millidegree Celsius.
+    pub fn temp(&self) -> crate::error::Result<i32> {
+        crate::utils::read_value(&format!("{}/{}/temp", crate::utils::rootpath(ROOTPATH), self.name))
     }
 }
 
+/// Enumerate every thermal zone.
 pub fn enumerate() -> Vec<Thermal> {
-    fn is_thermal(entry: &walkdir::DirEntry) -> bool {
-        if entry.path().to_str().unwrap() == ROOTPATH {
+    fn is_thermal(rootpath: &str, entry: &walkdir::DirEntry) -> bool {
+        if entry.path().to_str().unwrap() == rootpath {
             return false;
         }
         let realpath = if entry.file_type().is_symlink() {
-            let paths = std::fs::read_link(entry.path()).unwrap();
-            std::path::Path::new(ROOTPATH).join(paths).canonicalize().unwrap()
+            match std::fs::read_link(entry.path()).and_then(|p| std::path::Path::new(rootpath).join(p).canonicalize()) {
+                Ok(p) => p,
+                Err(_) => return false,
+            }
         } else if entry.file_type().is_dir() {
             entry.path().to_path_buf()
         } else {
@@ -57,16 +73,19 @@ pub fn enumerate() -> Vec<Thermal> {
         };
 
         let re = regex::Regex::new(r"^thermal_zone\d{1,3}$").unwrap();
-        re.is_match(realpath.file_name().unwrap().to_str().unwrap())
+        realpath.file_name()
+            .map(|s| re.is_match(&s.to_string_lossy()))
+            .unwrap_or(false)
     }
 
     let mut thermals = Vec::new();
-    for entry in walkdir::WalkDir::new(ROOTPATH)
+    let rootpath = crate::utils::rootpath(ROOTPATH);
+    for entry in walkdir::WalkDir::new(&rootpath)
             .sort_by_file_name()
             .max_depth(1)
             .into_iter()
             .filter_map(Result::ok)
-            .filter(|e|  (e.file_type().is_dir() || e.file_type().is_symlink()) && is_thermal(e)) {
+            .filter(|e|  (e.file_type().is_dir() || e.file_type().is_symlink()) && is_thermal(&rootpath, e)) {
                 let full_path =  entry.path().to_str().expect("is not path").to_string();
                 if let Ok(thermal) = Thermal::new(&full_path) {
                     thermals.push(thermal);
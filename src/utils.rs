@@ -1,5 +1,36 @@
 use std::fs::File;
 use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+// sysfs/procfs root, "/" on a live system or a captured fixture tree
+static ROOT: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+/// Resolve every sysfs/procfs path against `root` instead of `/`.
+pub fn set_root(root: &str) {
+    *ROOT.write().unwrap() = Some(PathBuf::from(root));
+}
+
+/// The sysfs/procfs root set by [`set_root`], `/` by default.
+pub fn root() -> PathBuf {
+    ROOT.read().unwrap().clone().unwrap_or_else(|| PathBuf::from("/"))
+}
+
+/// Map a path on the target system into the root, e.g.
+/// `/sys/class/hwmon` -> `<root>/sys/class/hwmon`.
+pub fn rootpath(path: &str) -> String {
+    root().join(path.trim_start_matches('/')).to_str().unwrap().to_string()
+}
+
+/// Map a path inside the root back to the target system, e.g.
+/// `<root>/sys/class/hwmon` -> `/sys/class/hwmon`. Paths outside the root
+/// are returned unchanged.
+pub fn logicpath(path: &Path) -> String {
+    match path.strip_prefix(root()) {
+        Ok(p) => format!("/{}", p.to_str().unwrap()),
+        Err(_) => path.to_str().unwrap().to_string(),
+    }
+}
 
 pub fn read_line(path: &str) -> std::io::Result<String> {
     let input = File::open(path)?;
@@ -10,6 +41,12 @@ pub fn read_line(path: &str) -> std::io::Result<String> {
     Ok(line.trim().to_string())
 }
 
+/// Read a single value node such as `capacity` or `scaling_cur_freq`.
+pub fn read_value<T: std::str::FromStr>(path: &str) -> crate::error::Result<T> {
+    let raw_val = read_line(path).map_err(|e| crate::error::Error::from_io(path, e))?;
+    raw_val.parse::<T>().map_err(|_| crate::error::Error::Parse { path: path.to_string(), value: raw_val })
+}
+
 pub fn write_line(path: &str, val: &str) -> std::io::Result<()> {
     let mut input = File::create(path)?;
     let val_line = format!("{}\n", val);
@@ -25,10 +62,47 @@ pub fn read_all_line(path: &str) -> std::io::Result<String> {
     Ok(data)
 }
 
+/// `secs` since the unix epoch as `YYYYmmdd-HHMMSS` in UTC.
+pub fn utc_string(secs: u64) -> String {
+    // days to civil date, see http://howardhinnant.github.io/date_algorithms.html
+    let days = (secs / 86400) as i64;
+    let rem = secs % 86400;
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{:04}{:02}{:02}-{:02}{:02}{:02}", year, month, day, rem / 3600, rem % 3600 / 60, rem % 60)
+}
+
+/// A duration in seconds as e.g. `1h02m03s`.
+pub fn hms_string(secs: f64) -> String {
+    let secs = secs.round() as u64;
+    format!("{}h{:02}m{:02}s", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
 pub fn run_cmd(cmd: &str, args: Vec<&str>) -> std::io::Result<String> {
     let output = std::process::Command::new(cmd)
         .args(args)
         .output()?;
 
     Ok(String::from_utf8_lossy(&output.stdout).to_string())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn utc_string_formats_civil_time() {
+        assert_eq!(utc_string(0), "19700101-000000");
+        assert_eq!(utc_string(951782400), "20000229-000000");
+        assert_eq!(utc_string(951868799), "20000229-235959");
+        assert_eq!(utc_string(1792314123), "20261018-090203");
+        assert_eq!(utc_string(4102444800), "21000101-000000");
+    }
+}
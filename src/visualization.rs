@@ -1,40 +1,139 @@
 //use plotters::prelude::*;
-use charts_rs::{Box, LineChart, Series};
+use charts_rs::{Box, LineChart, Series, NIL_VALUE};
 use std::io::Write;
 
 // cpufreq.csv -> cpufreq.svg
 // capacity.csv -> capacity.svg
+/// Render every column of a collector csv as a line chart titled `desc`.
 pub fn show_datas(infile: &str, outfile: &str, desc: &str) -> std::io::Result<()> {
-    let mut rdr = csv::Reader::from_path(infile)?;
+    let (series_list, x_axis_data) = read_datas(infile)?;
+    write_chart(series_list, x_axis_data, infile, outfile, desc, false)
+}
+
+// the charts of a run directory: csv name, chart title and whether the
+// columns are stacked
+const CHARTS: [(&str, &str, bool); 12] = [
+    ("cpufreq", "show cpu freq chart", false),
+    ("cpuload", "show cpu utilization chart", false),
+    ("cstate", "show cpu idle residency chart", true),
+    ("memory", "show memory chart", false),
+    ("pressure", "show pressure stall chart", false),
+    ("disk", "show disk io chart", false),
+    ("gpu", "show gpu chart", false),
+    ("net", "show network chart", false),
+    ("rapl", "show rapl power chart", false),
+    ("capacity", "show battery capacity chart", false),
+    ("power", "show battery power chart", false),
+    ("thermal", "show thermal chart", false),
+];
+
+/// Render every csv of the run directory `dir` next to it, warning about
+/// the ones that can't be read.
+pub fn show_run(dir: &std::path::Path) {
+    let path = |name: &str| dir.join(name).to_string_lossy().to_string();
+
+    for (name, desc, stacked) in CHARTS {
+        let (csv, svg) = (path(&format!("{}.csv", name)), path(&format!("{}.svg", name)));
+        let result = if stacked {
+            show_stacked(&csv, &svg, desc)
+        } else {
+            show_datas(&csv, &svg, desc)
+        };
+        if let Err(e) = result {
+            println!("WARNING: {}", e);
+        }
+    }
+    for entry in walkdir::WalkDir::new(dir)
+            .sort_by_file_name()
+            .max_depth(1)
+            .into_iter()
+            .filter_map(Result::ok) {
+                let name = entry.file_name().to_string_lossy().to_string();
+                if let Some(chip) = name.strip_prefix(crate::run::HWMON_CSV_PREFIX).and_then(|n| n.strip_suffix(".csv")) {
+                    let svg = path(&format!("{}{}.svg", crate::run::HWMON_CSV_PREFIX, chip));
+                    if let Err(e) = show_datas(&path(&name), &svg, &format!("show hwmon {} chart", chip)) {
+                        println!("WARNING: {}", e);
+                    }
+                }
+    }
+}
+
+// cstate.csv -> cstate_0_0_0.svg, cstate_0_1_1.svg, ...
+/// Render the `group:series` columns of a collector csv as one stacked
+/// area chart per group, e.g. the cpuidle states of each cpu.
+pub fn show_stacked(infile: &str, outfile: &str, desc: &str) -> std::io::Result<()> {
+    let (series_list, x_axis_data) = read_datas(infile)?;
+    let mut groups: Vec<(String, Vec<Series>)> = Vec::new();
+    for mut series in series_list {
+        let (group, name) = match series.name.rsplit_once(':') {
+            Some((group, name)) => (group.to_string(), name.to_string()),
+            None => (String::new(), series.name.clone()),
+        };
+        series.name = name;
+        match groups.iter_mut().find(|(g, _)| *g == group) {
+            Some((_, list)) => list.push(series),
+            None => groups.push((group, vec![series])),
+        }
+    }
+
+    let stem = outfile.strip_suffix(".svg").unwrap_or(outfile);
+    for (group, mut list) in groups {
+        // each series becomes the running sum of itself and the ones before it
+        for i in 1..list.len() {
+            let (done, rest) = list.split_at_mut(i);
+            for (j, val) in rest[0].data.iter_mut().enumerate() {
+                let below = done[i - 1].data.get(j).copied().unwrap_or(NIL_VALUE);
+                if *val != NIL_VALUE && below != NIL_VALUE {
+                    *val += below;
+                }
+            }
+        }
+        // filled areas are painted in order, the top one goes first
+        list.reverse();
+        let file = format!("{}_{}.svg", stem, group.replace([':', '/'], "_"));
+        write_chart(list, x_axis_data.clone(), infile, &file, &format!("{} {}", desc, group), true)?;
+    }
+    Ok(())
+}
+
+fn read_datas(infile: &str) -> std::io::Result<(Vec<Series>, Vec<String>)> {
+    let mut rdr = csv::Reader::from_path(infile).map_err(|e| crate::error::Error::from_csv(infile, e))?;
     let mut series_list = Vec::new();
     let mut x_axis_data = Vec::new();
+    // timestamp_ms,elapsed_ms,... ; x axis is the elapsed time in seconds
+    let time_columns = crate::collector::Timestamp::header().len();
+    // the stop column only holds text in the final record
+    let mut columns = Vec::new();
     {
-        let headers = rdr.headers()?;
-        let mut index = 0;
-        for r in headers.into_iter() {
-            if index > 0 {
+        let headers = rdr.headers().map_err(|e| crate::error::Error::from_csv(infile, e))?;
+        for (index, r) in headers.into_iter().enumerate() {
+            if index >= time_columns && r != crate::collector::STOP_COLUMN {
                 let tag = r.to_string();
                 series_list.push(Series::new(tag, Vec::new()));
+                columns.push(index);
             }
-            index = index + 1;
         }
     }
     
     for result in rdr.records() {
-        let record = result.unwrap();
-        for i in 0..record.len() {
-            if i == 0 {
-                x_axis_data.push( record[i].to_string());
-            } else {
-                series_list[i - 1].data.push(record[i].parse::<i32>().unwrap() as f32);
-                //series_list[i - 1].label_show = true
-            }
+        let record = result.map_err(|e| crate::error::Error::from_csv(infile, e))?;
+        let elapsed = record.get(time_columns - 1).and_then(|v| v.parse::<f64>().ok()).unwrap_or_default() / 1000.0;
+        x_axis_data.push(format!("{:.1}", elapsed));
+        for (series, &i) in series_list.iter_mut().zip(columns.iter()) {
+            // empty cell: the reading failed at that sample
+            let val = record.get(i).and_then(|v| v.parse::<f32>().ok()).unwrap_or(NIL_VALUE);
+            series.data.push(val);
         }
 
     }
+    Ok((series_list, x_axis_data))
+}
+
+fn write_chart(series_list: Vec<Series>, x_axis_data: Vec<String>, infile: &str, outfile: &str, desc: &str, fill: bool) -> std::io::Result<()> {
     let mut line_chart = LineChart::new(series_list, x_axis_data);
-    line_chart.title_text = "Stacked Area Chart".to_string();
-    line_chart.sub_title_text = "Hello World".to_string();
+    line_chart.title_text = desc.to_string();
+    line_chart.sub_title_text = infile.to_string();
+    line_chart.series_fill = fill;
     line_chart.legend_margin = Some(Box {
         top: 50.0,
         bottom: 10.0,
@@ -50,4 +149,4 @@ pub fn show_datas(infile: &str, outfile: &str, desc: &str) -> std::io::Result<()
     }
     
     Ok(())
-}
\ No newline at end of file
+}
@@ -0,0 +1,360 @@
+// Enumerators and two samples of every collector against a copy of the
+// captured tree in tests/fixtures/laptop
+use system_monitor::{battery, block, collector, config, cpu, gpu, hwmon, interrupts, net, rapl, thermal, utils};
+use system_monitor::collector::Collector;
+
+// the root is global, one test at a time
+static ROOT: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+// a copy of the fixture tree as root, so a test can advance its counters
+// between two updates, and a directory for the csv files; both are removed
+// on drop
+struct Fixture {
+    dir: std::path::PathBuf,
+    _root: std::sync::MutexGuard<'static, ()>,
+}
+
+fn fixture(test: &str) -> Fixture {
+    let lock = ROOT.lock().unwrap_or_else(|e| e.into_inner());
+    let dir = std::env::temp_dir().join(format!("system-monitor-{}-{}", test, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    copy_tree(std::path::Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/laptop")), &dir.join("root"));
+    std::fs::create_dir(dir.join("out")).unwrap();
+    utils::set_root(dir.join("root").to_str().unwrap());
+    Fixture { dir, _root: lock }
+}
+
+// like `cp -a`: sysfs is all symlinks, keep them as they are
+fn copy_tree(from: &std::path::Path, to: &std::path::Path) {
+    std::fs::create_dir_all(to).unwrap();
+    for entry in std::fs::read_dir(from).unwrap() {
+        let entry = entry.unwrap();
+        let target = to.join(entry.file_name());
+        let kind = entry.file_type().unwrap();
+        if kind.is_symlink() {
+            std::os::unix::fs::symlink(std::fs::read_link(entry.path()).unwrap(), &target).unwrap();
+        } else if kind.is_dir() {
+            copy_tree(&entry.path(), &target);
+        } else {
+            std::fs::copy(entry.path(), &target).unwrap();
+        }
+    }
+}
+
+impl Fixture {
+    // a csv file in the output directory
+    fn path(&self, name: &str) -> String {
+        self.dir.join("out").join(name).to_string_lossy().to_string()
+    }
+
+    // remove a file of the copied tree, as if the kernel didn't report it
+    fn remove(&self, file: &str) {
+        std::fs::remove_file(self.dir.join("root").join(file)).unwrap();
+    }
+
+    // replace `from` with `to` in a file of the copied tree
+    fn replace(&self, file: &str, from: &str, to: &str) {
+        let path = self.dir.join("root").join(file);
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert!(contents.contains(from), "{} has no {:?}", file, from);
+        std::fs::write(&path, contents.replacen(from, to, 1)).unwrap();
+    }
+}
+
+impl Drop for Fixture {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+// two samples a second apart, so delta collectors write a rate; `advance`
+// moves the counters of the tree in between
+fn sample(c: &mut dyn Collector, advance: impl FnOnce()) {
+    c.update(&collector::Timestamp { millis: 1_000_000, elapsed: 0 }).unwrap();
+    advance();
+    c.update(&collector::Timestamp { millis: 1_001_000, elapsed: 1000 }).unwrap();
+}
+
+fn value<'a>(c: &'a dyn Collector, column: &str) -> Option<&'a str> {
+    c.output().value(column)
+}
+
+#[test]
+fn enumerate_cpus() {
+    let _f = fixture("cpus");
+    let cpus = cpu::enumerate();
+    assert_eq!(cpus.len(), 1, "cpu1 has no cpufreq");
+    assert_eq!(cpus[0].tag(), "0:0:0");
+    assert_eq!(cpus[0].scaling_governor(), "powersave");
+    assert_eq!(cpus[0].freq().unwrap(), 1234567);
+
+    let all: Vec<String> = cpu::enumerate_all().iter().map(|c| c.tag()).collect();
+    assert_eq!(all, vec!["0:0:0", "0:1:1"]);
+    let states = cpu::enumerate_all()[1].idle_states().unwrap();
+    assert_eq!(states.iter().map(|s| s.name.as_str()).collect::<Vec<_>>(), vec!["POLL", "C1E"]);
+
+    let stat = cpu::stat().unwrap();
+    assert_eq!(stat.cpus.len(), 2);
+    assert_eq!(stat.ctxt, 209292921);
+}
+
+#[test]
+fn enumerate_devices() {
+    let _f = fixture("devices");
+    let bats = battery::enumerate();
+    assert_eq!(bats.iter().map(|b| b.name.as_str()).collect::<Vec<_>>(), vec!["BAT0", "BAT1"]);
+    assert_eq!(bats[0].current_now().unwrap(), -500000);
+    // 12 V * 0.5 A without power_now
+    assert_eq!(bats[0].power_now().unwrap(), 6_000_000);
+    assert_eq!(bats[1].power_now().unwrap(), 2_360_000);
+    assert_eq!(bats[0].energy().unwrap(), (40.0, 50.0));
+    assert_eq!(bats[1].energy().unwrap(), (5.0, 20.0));
+    assert_eq!(battery::enumerate_mains().iter().map(|m| m.name.as_str()).collect::<Vec<_>>(), vec!["AC"]);
+    assert!(battery::on_battery());
+
+    let thermals = thermal::enumerate();
+    assert_eq!(thermals.len(), 1);
+    assert_eq!(thermals[0].temp().unwrap(), 45000);
+
+    let ids: Vec<String> = hwmon::enumerate().iter().map(|h| h.id.clone()).collect();
+    assert_eq!(ids, vec!["platform/coretemp.0", "platform/thinkpad_hwmon", "virtual/thermal/thermal_zone0"]);
+
+    assert_eq!(block::enumerate(), vec!["nvme0n1"]);
+    assert_eq!(net::enumerate(), vec!["wlan0"]);
+
+    let zones: Vec<String> = rapl::enumerate().iter().map(|z| z.name.clone()).collect();
+    assert_eq!(zones, vec!["mmio/package-0", "package-0", "package-0/core"]);
+
+    let gpus = gpu::enumerate();
+    assert_eq!(gpus.len(), 1, "connectors are not cards");
+    assert_eq!(gpus[0].driver, "i915");
+    assert_eq!(gpus[0].kind, gpu::GpuKind::Intel);
+
+    assert_eq!(interrupts::interrupts().unwrap().cpus, vec![0, 1]);
+}
+
+#[test]
+fn enumerate_config() {
+    let _f = fixture("config");
+    let configs = config::enumerate();
+    let value = |node: &str| configs.iter().find(|c| c.node == node).and_then(|c| c.value.clone());
+    assert_eq!(value("/sys/devices/system/cpu/cpu0/cpufreq/scaling_governor").as_deref(), Some("powersave"));
+    assert_eq!(value("/sys/class/power_supply/BAT0/charge_control_end_threshold").as_deref(), Some("80"));
+    assert!(configs.iter().all(|c| !c.node.contains("hwmon")));
+}
+
+#[test]
+fn collect_cpu() {
+    let f = fixture("cpu");
+    let path = |name: &str| f.path(name);
+
+    let mut c = collector::CpuCollector::new(cpu::enumerate(), &path("cpufreq.csv")).unwrap();
+    sample(&mut c, || f.replace("sys/devices/system/cpu/cpu0/cpufreq/scaling_cur_freq", "1234567", "2400000"));
+    assert_eq!(value(&c, "0:0:0"), Some("2400000"));
+    let csv = std::fs::read_to_string(path("cpufreq.csv")).unwrap();
+    assert!(csv.contains(",1234567,\n") && csv.ends_with(",2400000,\n"), "{}", csv);
+
+    let mut c = collector::CpuLoadCollector::new(cpu::enumerate_all(), &path("cpuload.csv")).unwrap();
+    sample(&mut c, || {
+        // 100 ticks: 30 user, 10 system, 60 idle, all of them on cpu1
+        f.replace("proc/stat", "cpu  10132153 290696 3084719 46828483", "cpu  10132183 290696 3084729 46828543");
+        f.replace("proc/stat", "cpu1 5066077 145348 1542360 23414242", "cpu1 5066107 145348 1542370 23414302");
+    });
+    assert_eq!(value(&c, "all:user"), Some("30.0"));
+    assert_eq!(value(&c, "all:system"), Some("10.0"));
+    assert_eq!(value(&c, "all:idle"), Some("60.0"));
+    assert_eq!(value(&c, "0:1:1:user"), Some("30.0"));
+    assert_eq!(value(&c, "0:1:1:idle"), Some("60.0"));
+    assert_eq!(value(&c, "0:1:1:iowait"), Some("0.0"));
+    // no ticks on cpu0, no utilization
+    assert_eq!(value(&c, "0:0:0:idle"), None);
+
+    let mut c = collector::CStateCollector::new(cpu::enumerate_all(), &path("cstate.csv")).unwrap();
+    // 10 ms in POLL and 600 ms in C1E of the second
+    sample(&mut c, || {
+        f.replace("sys/devices/system/cpu/cpu1/cpuidle/state0/time", "1000", "11000");
+        f.replace("sys/devices/system/cpu/cpu1/cpuidle/state1/time", "123457789", "124057789");
+    });
+    assert_eq!(value(&c, "0:1:1:POLL"), Some("1.0"));
+    assert_eq!(value(&c, "0:1:1:C1E"), Some("60.0"));
+    assert_eq!(value(&c, "0:1:1:active"), Some("39.0"));
+}
+
+#[test]
+fn collect_system() {
+    let f = fixture("system");
+    let path = |name: &str| f.path(name);
+
+    let mut c = collector::MemoryCollector::new(&path("memory.csv")).unwrap();
+    sample(&mut c, || {
+        f.replace("proc/vmstat", "pgpgin 4512345", "pgpgin 4513369");
+        f.replace("proc/vmstat", "pgfault 123456789", "pgfault 123459289");
+    });
+    assert_eq!(value(&c, "SwapUsed_kB"), Some("262144"));
+    assert_eq!(value(&c, "pgpgin/s"), Some("1024.0"));
+    assert_eq!(value(&c, "pgfault/s"), Some("2500.0"));
+    assert_eq!(value(&c, "pswpout/s"), Some("0.0"));
+
+    let mut c = collector::PressureCollector::new(&path("pressure.csv")).unwrap();
+    // stalled for 150 ms and 25 ms of the second
+    sample(&mut c, || {
+        f.replace("proc/pressure/cpu", "total=12345678", "total=12495678");
+        f.replace("proc/pressure/io", "total=543210", "total=568210");
+    });
+    assert_eq!(value(&c, "cpu:some_stall%"), Some("15.00"));
+    assert_eq!(value(&c, "cpu:full_stall%"), Some("0.00"));
+    assert_eq!(value(&c, "io:full_stall%"), Some("2.50"));
+    assert_eq!(value(&c, "io:full_avg10"), Some("0.10"));
+    assert_eq!(value(&c, "running"), Some("2"));
+
+    let cpus = interrupts::interrupts().unwrap().cpus;
+    let mut c = collector::InterruptCollector::new(cpus, 3, &path("interrupts.csv")).unwrap();
+    sample(&mut c, || {
+        f.replace("proc/stat", "ctxt 209292921", "ctxt 209297921");
+        f.replace("proc/interrupts", "LOC:    7213041    6912876", "LOC:    7213841    6913076");
+        f.replace("proc/interrupts", "145:     183012      90211", "145:     183012      90511");
+        f.replace("proc/softirqs", "TIMER:    1056718    1056756", "TIMER:    1057718    1056956");
+    });
+    assert_eq!(value(&c, "ctxt/s"), Some("5000.0"));
+    assert_eq!(value(&c, "irq/s"), Some("1300.0"));
+    assert_eq!(value(&c, "softirq/s"), Some("1200.0"));
+    assert_eq!(value(&c, "cpu0:irq/s"), Some("800.0"));
+    assert_eq!(value(&c, "cpu1:irq/s"), Some("500.0"));
+    assert_eq!(value(&c, "cpu0:softirq/s"), Some("1000.0"));
+    assert_eq!(value(&c, "cpu1:softirq/s"), Some("200.0"));
+    let top: Vec<Option<&str>> = ["source", "/s", "cpu"].iter()
+        .flat_map(|column| (1..=3).map(move |i| format!("top{}:{}", i, column)))
+        .map(|column| value(&c, &column))
+        .collect();
+    assert_eq!(top, vec![
+        Some("softirq/TIMER"), Some("LOC"), Some("145/xhci_hcd"),
+        Some("1200.0"), Some("1000.0"), Some("300.0"),
+        Some("0"), Some("0"), Some("1"),
+    ]);
+
+    let mut c = collector::DiskCollector::new(block::enumerate(), &path("disk.csv")).unwrap();
+    // 100 reads of 2048 sectors in 50 ms, 50 writes of 1024 sectors in
+    // 100 ms, busy for 250 ms
+    sample(&mut c, || f.replace("proc/diskstats",
+        "nvme0n1 312061 98011 19732246 61234 912345 512345 45123456 823456 0 712345",
+        "nvme0n1 312161 98011 19734294 61284 912395 512345 45124480 823556 0 712595"));
+    assert_eq!(value(&c, "nvme0n1:rkB/s"), Some("1024.0"));
+    assert_eq!(value(&c, "nvme0n1:wkB/s"), Some("512.0"));
+    assert_eq!(value(&c, "nvme0n1:r/s"), Some("100.0"));
+    assert_eq!(value(&c, "nvme0n1:w/s"), Some("50.0"));
+    assert_eq!(value(&c, "nvme0n1:util%"), Some("25.0"));
+    assert_eq!(value(&c, "nvme0n1:await_ms"), Some("1.00"));
+
+    let mut c = collector::NetCollector::new(net::enumerate(), &path("net.csv")).unwrap();
+    sample(&mut c, || f.replace("proc/net/dev",
+        "wlan0: 987654321  765432    0   12    0     0          0      1234 123456789  234567",
+        "wlan0: 987804321  765532    0   14    0     0          0      1234 123476789  234592"));
+    assert_eq!(value(&c, "wlan0:rx_bytes/s"), Some("150000.0"));
+    assert_eq!(value(&c, "wlan0:tx_bytes/s"), Some("20000.0"));
+    assert_eq!(value(&c, "wlan0:rx_packets/s"), Some("100.0"));
+    assert_eq!(value(&c, "wlan0:tx_packets/s"), Some("25.0"));
+    // a count within the interval, not a rate
+    assert_eq!(value(&c, "wlan0:rx_drop"), Some("2"));
+    assert_eq!(value(&c, "wlan0:rx_errs"), Some("0"));
+}
+
+#[test]
+fn collect_devices() {
+    let f = fixture("devices");
+    let path = |name: &str| f.path(name);
+
+    let mut c = collector::GpuCollector::new(gpu::enumerate(), &path("gpu.csv")).unwrap();
+    sample(&mut c, || f.replace("sys/class/drm/card0/power/rc6_residency_ms", "9876543", "9877293"));
+    assert_eq!(value(&c, "card0:cur_freq_MHz"), Some("300"));
+    assert_eq!(value(&c, "card0:rc6%"), Some("75.0"));
+
+    let mut c = collector::RaplCollector::new(rapl::enumerate(), &path("rapl.csv")).unwrap();
+    sample(&mut c, || {
+        // the package counter wraps at max_energy_range_uj
+        f.replace("sys/class/powercap/intel-rapl:0/energy_uj", "262143000000", "14671150");
+        f.replace("sys/class/powercap/intel-rapl:0:0/energy_uj", "12345678", "18845678");
+        f.replace("sys/class/powercap/intel-rapl-mmio:0/energy_uj", "1000", "15001000");
+    });
+    assert_eq!(value(&c, "package-0"), Some("15.000"));
+    assert_eq!(value(&c, "package-0/core"), Some("6.500"));
+    assert_eq!(value(&c, "mmio/package-0"), Some("15.000"));
+
+    let bats = battery::enumerate();
+    let mut c = collector::CapacityCollector::new(bats.clone(), &path("capacity.csv"), 5).unwrap();
+    sample(&mut c, || ());
+    assert_eq!(value(&c, "BAT0"), Some("80"));
+    assert_eq!(value(&c, "BAT1"), Some("25"));
+    // 45 of 70 Wh, not the 52.5 average of 80% and 25%
+    assert_eq!(value(&c, "combined"), Some("64.3"));
+    assert_eq!(c.need_stop(), None);
+
+    let mut c = collector::PowerCollector::new(bats.clone(), &path("power.csv")).unwrap();
+    sample(&mut c, || ());
+    assert_eq!(value(&c, "BAT0"), Some("6000000"));
+    assert_eq!(value(&c, "BAT1"), Some("2360000"));
+
+    let mut c = collector::BatteryCollector::new(bats, battery::enumerate_mains(), &path("battery.csv")).unwrap();
+    // plugged in between the two
+    sample(&mut c, || {
+        f.replace("sys/class/power_supply/BAT0/status", "Discharging", "Charging");
+        f.replace("sys/class/power_supply/BAT0/current_now", "500000", "1500000");
+        f.replace("sys/class/power_supply/BAT0/energy_now", "40000000", "40010000");
+        f.replace("sys/class/power_supply/AC/online", "0", "1");
+    });
+    let csv = std::fs::read_to_string(path("battery.csv")).unwrap();
+    assert_eq!(csv.lines().next(), Some(concat!("timestamp_ms,elapsed_ms,",
+        "BAT0:status,BAT0:energy_uWh,BAT0:charge_uAh,BAT0:voltage_uV,BAT0:current_uA,",
+        "BAT1:status,BAT1:energy_uWh,BAT1:charge_uAh,BAT1:voltage_uV,BAT1:current_uA,AC:online,stop")));
+    assert!(csv.contains("\n1000000,0,Discharging,40000000,,12000000,-500000,Discharging,5000000,,11800000,-200000,0,\n"), "{}", csv);
+    assert!(csv.ends_with("\n1001000,1000,Charging,40010000,,12000000,1500000,Discharging,5000000,,11800000,-200000,1,\n"), "{}", csv);
+
+    let mut c = collector::ThermalCollector::new(thermal::enumerate(), &path("thermal.csv")).unwrap();
+    sample(&mut c, || ());
+    assert_eq!(value(&c, "thermal_zone0"), Some("45000"));
+
+    for chip in hwmon::enumerate() {
+        for class in [hwmon::SensorClass::Temp, hwmon::SensorClass::Fan] {
+            if !chip.sensors().iter().any(|s| s.class == class) {
+                continue;
+            }
+            let file = path(&format!("hwmon_{}_{}.csv", chip.id.replace(['/', ':'], "_"), class.prefix()));
+            let mut c = collector::HwmonCollector::new(&chip, class, &file).unwrap();
+            sample(&mut c, || ());
+        }
+    }
+    let temps = std::fs::read_to_string(path("hwmon_platform_coretemp.0_temp.csv")).unwrap();
+    assert!(temps.starts_with("timestamp_ms,elapsed_ms,platform/coretemp.0:Package id 0,platform/coretemp.0:Core 0,stop\n"));
+    assert!(temps.ends_with(",47000,45000,\n"));
+}
+
+#[test]
+fn capacity_without_energy() {
+    let f = fixture("capacity");
+    f.remove("sys/class/power_supply/BAT1/energy_now");
+    let mut c = collector::CapacityCollector::new(battery::enumerate(), &f.path("capacity.csv"), 60).unwrap();
+    sample(&mut c, || ());
+    // BAT1 reports neither energy nor charge, so the plain average of
+    // 80% and 25%
+    assert_eq!(value(&c, "combined"), Some("52.5"));
+    assert_eq!(c.need_stop(), Some("capacity.combined <= 60".to_string()));
+}
+
+#[test]
+fn battery_health() {
+    let _f = fixture("health");
+    let healths: Vec<battery::Health> = battery::enumerate().iter().map(|b| b.health()).collect();
+    // 50 of 57 Wh
+    assert_eq!(healths[0].cycle_count, Some(87));
+    assert_eq!(healths[0].full_wh, Some(50.0));
+    assert_eq!(healths[0].design_wh, Some(57.0));
+    assert_eq!(healths[0].health_percent.map(|p| format!("{:.1}", p)).as_deref(), Some("87.7"));
+    assert_eq!(healths[0].wear_percent.map(|p| format!("{:.1}", p)).as_deref(), Some("12.3"));
+
+    // BAT1 has no energy_full_design nor cycle_count
+    assert_eq!(healths[1].cycle_count, None);
+    assert_eq!(healths[1].full_wh, Some(20.0));
+    assert_eq!(healths[1].design_wh, None);
+    assert_eq!(healths[1].health_percent, None);
+    assert_eq!(healths[1].wear_percent, None);
+    assert!(healths[1].to_string().contains("health:          unknown"));
+}